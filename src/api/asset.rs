@@ -0,0 +1,57 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use jsonrpsee::types::Params;
+use jsonrpsee::{Extensions, ResponsePayload};
+use serde::{Deserialize, Serialize};
+
+use crate::crawler::Crawler;
+
+pub async fn rpc_guid_to_path_handler(
+    params: Params<'static>,
+    context: Arc<Crawler>,
+    _extensions: Extensions,
+) -> ResponsePayload<'static, Option<String>> {
+    log::debug!("Handling guid_to_path request");
+
+    let param: GuidParam = match params.parse() {
+        Ok(p) => p,
+        Err(e) => return ResponsePayload::error(e),
+    };
+
+    let assets = context.assets.read().await;
+
+    ResponsePayload::success(
+        assets
+            .path(&param.guid)
+            .map(|path| path.to_string_lossy().to_string()),
+    )
+}
+
+pub async fn rpc_path_to_guid_handler(
+    params: Params<'static>,
+    context: Arc<Crawler>,
+    _extensions: Extensions,
+) -> ResponsePayload<'static, Option<String>> {
+    log::debug!("Handling path_to_guid request");
+
+    let param: PathParam = match params.parse() {
+        Ok(p) => p,
+        Err(e) => return ResponsePayload::error(e),
+    };
+
+    let asset = context.resolve_path(Path::new(&param.path));
+    let assets = context.assets.read().await;
+
+    ResponsePayload::success(assets.guid(&asset).map(str::to_owned))
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Deserialize, Serialize)]
+pub struct GuidParam {
+    pub guid: String,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PathParam {
+    pub path: String,
+}
@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use jsonrpsee::types::Params;
+use jsonrpsee::{Extensions, ResponsePayload};
+use serde::{Deserialize, Serialize};
+
+use crate::api::object::guid_for_path;
+use crate::crawler::{Crawler, ObjectDefinition, Reference};
+
+pub async fn rpc_component_usages_handler(
+    params: Params<'static>,
+    context: Arc<Crawler>,
+    _extensions: Extensions,
+) -> ResponsePayload<'static, Vec<ComponentUsageResponse>> {
+    log::debug!("Handling component usages request");
+
+    let component: ComponentParam = match params.parse() {
+        Ok(c) => c,
+        Err(e) => return ResponsePayload::error(e),
+    };
+
+    let script_guids = match component {
+        ComponentParam::Guid { guid } => vec![guid],
+        ComponentParam::Path { path } => match guid_for_path(&context, &path).await {
+            Ok(guid) => vec![guid],
+            Err(e) => return ResponsePayload::error(e),
+        },
+        ComponentParam::ClassName { class_name } => {
+            let scripts = context.scripts.read().await;
+            let assets = context.assets.read().await;
+
+            scripts
+                .iter()
+                .filter(|(_, class)| class_name_matches(class, &class_name))
+                .filter_map(|(path, _)| assets.guid(path).map(str::to_owned))
+                .collect()
+        }
+    };
+
+    let all_refs = context.component_refs.read().await;
+
+    let mut usages = Vec::new();
+
+    for guid in script_guids {
+        let class = script_class(&context, &guid).await;
+
+        let Some(component_refs) = all_refs.get(&ObjectDefinition { guid }) else {
+            continue;
+        };
+
+        usages.extend(component_refs.iter().map(|r| ComponentUsageResponse {
+            script: class.clone(),
+            ..r.clone().into()
+        }));
+    }
+
+    ResponsePayload::success(usages)
+}
+
+/// Classes can be searched for by either their fully qualified name or just the
+/// name of the class itself
+fn class_name_matches(class: &str, search: &str) -> bool {
+    class == search
+        || class
+            .rsplit_once('.')
+            .is_some_and(|(_, short_name)| short_name == search)
+}
+
+async fn script_class(context: &Crawler, guid: &str) -> Option<String> {
+    let assets = context.assets.read().await;
+    let path = assets.path(guid)?;
+
+    context.scripts.read().await.get(path).cloned()
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ComponentParam {
+    Guid { guid: String },
+    Path { path: String },
+    ClassName { class_name: String },
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ComponentUsageResponse {
+    pub file: String,
+    pub line: Option<usize>,
+    pub asset: Option<String>,
+    pub object: Option<Vec<String>>,
+    pub package: Option<String>,
+    pub script: Option<String>,
+}
+
+impl From<Reference> for ComponentUsageResponse {
+    fn from(value: Reference) -> Self {
+        Self {
+            file: value.file.to_string_lossy().to_string(),
+            line: value.line,
+            asset: value.asset,
+            object: value.object,
+            package: value.package,
+            script: None,
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::sync::Arc;
+
+use jsonrpsee::types::Params;
+use jsonrpsee::{Extensions, ResponsePayload};
+use serde::{Deserialize, Serialize};
+
+use crate::api::object::ObjectParam;
+use crate::crawler::{guids_by_file, Crawler};
+use crate::metafile::is_builtin_guid;
+
+pub async fn rpc_dependencies_handler(
+    params: Params<'static>,
+    context: Arc<Crawler>,
+    _extensions: Extensions,
+) -> ResponsePayload<'static, BTreeMap<String, Vec<DependencyResponse>>> {
+    log::debug!("Handling dependencies request");
+
+    let dependencies: DependenciesParam = match params.parse() {
+        Ok(d) => d,
+        Err(e) => return ResponsePayload::error(e),
+    };
+
+    let assets = context.assets.read().await;
+
+    // Paths are used as they are, so assets without a .meta file can be
+    // checked as well
+    let asset = match dependencies.asset {
+        ObjectParam::Guid { guid } => match assets.path(&guid) {
+            Some(path) => path.to_path_buf(),
+            None => return ResponsePayload::success(BTreeMap::new()),
+        },
+        ObjectParam::Path { path } => context.resolve_path(Path::new(&path)),
+    };
+
+    let file_dependencies = {
+        let object_refs = context.object_refs.read().await;
+
+        if dependencies.transitive {
+            guids_by_file(&object_refs)
+        } else {
+            // Only the requested file is needed, so the other files are not
+            // inverted
+            let guids = object_refs
+                .iter()
+                .filter(|(_, object_refs)| object_refs.iter().any(|r| r.file == asset))
+                .map(|(object, _)| object.guid.clone())
+                .collect();
+
+            HashMap::from([(asset.clone(), guids)])
+        }
+    };
+
+    let mut grouped: BTreeMap<String, Vec<DependencyResponse>> = BTreeMap::new();
+    let mut visited: HashSet<String> = assets.guid(&asset).map(str::to_owned).into_iter().collect();
+    let mut queue = VecDeque::from([(asset, 1)]);
+
+    while let Some((file, depth)) = queue.pop_front() {
+        let Some(guids) = file_dependencies.get(&file) else {
+            continue;
+        };
+
+        let mut guids: Vec<&String> = guids.iter().collect();
+        guids.sort();
+
+        for dependency in guids {
+            if !visited.insert(dependency.clone()) {
+                continue;
+            }
+
+            let path = assets.path(dependency);
+
+            let group = match path {
+                _ if is_builtin_guid(dependency) => "builtin".to_owned(),
+                Some(path) => path
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().to_lowercase())
+                    .unwrap_or_else(|| "other".to_owned()),
+                None => "missing".to_owned(),
+            };
+
+            grouped.entry(group).or_default().push(DependencyResponse {
+                guid: dependency.clone(),
+                path: path.map(|p| p.to_string_lossy().to_string()),
+                depth,
+            });
+
+            if dependencies.transitive {
+                if let Some(path) = path {
+                    queue.push_back((path.to_path_buf(), depth + 1));
+                }
+            }
+        }
+    }
+
+    ResponsePayload::success(grouped)
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DependenciesParam {
+    #[serde(flatten)]
+    pub asset: ObjectParam,
+
+    /// Also include the dependencies of dependencies
+    #[serde(default)]
+    pub transitive: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DependencyResponse {
+    pub guid: String,
+
+    /// Where the asset is, if it exists in the project
+    pub path: Option<String>,
+
+    /// 1 for assets referenced by the requested asset itself, 2 for the
+    /// dependencies of those, and so on
+    pub depth: usize,
+}
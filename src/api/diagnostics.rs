@@ -0,0 +1,146 @@
+use std::sync::Arc;
+
+use jsonrpsee::types::Params;
+use jsonrpsee::{Extensions, ResponsePayload};
+use serde::{Deserialize, Serialize};
+
+use crate::api::status::require_ready;
+use crate::crawler::{Crawler, Reference, ReferenceKind};
+use crate::metafile::is_builtin_guid;
+
+pub async fn rpc_diagnostics_handler(
+    _params: Params<'static>,
+    context: Arc<Crawler>,
+    _extensions: Extensions,
+) -> ResponsePayload<'static, Vec<DiagnosticResponse>> {
+    log::debug!("Handling diagnostics request");
+
+    // The GUIDs of assets that are not indexed yet would show up as missing
+    if let Err(e) = require_ready(&context).await {
+        return ResponsePayload::error(e);
+    }
+
+    let assets = context.assets.read().await;
+
+    // The assets of packages that are not indexed are unknown, so a GUID that
+    // cannot be found may belong to one of them
+    let verified = context.unindexed_packages.is_empty();
+
+    let mut found: Vec<DiagnosticResponse> = context
+        .object_refs
+        .read()
+        .await
+        .iter()
+        .filter(|(object, _)| !is_builtin_guid(&object.guid) && assets.path(&object.guid).is_none())
+        .flat_map(|(object, object_refs)| {
+            object_refs
+                .iter()
+                .map(|r| DiagnosticResponse::new(object.guid.clone(), r.clone(), verified))
+        })
+        .collect();
+
+    found.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+
+    ResponsePayload::success(found)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticKind {
+    /// A component whose script has no `.meta` file in the project
+    MissingScript,
+
+    /// A reference to an asset that has no `.meta` file in the project
+    MissingReference,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DiagnosticResponse {
+    pub kind: DiagnosticKind,
+
+    /// The GUID that could not be found
+    pub guid: String,
+
+    pub file: String,
+    pub line: Option<usize>,
+    pub asset: Option<String>,
+    pub object: Option<Vec<String>>,
+    pub package: Option<String>,
+
+    /// The serialized property holding the reference
+    /// (`m_Materials.Array.data[0]`)
+    pub property: Option<String>,
+
+    /// False if some packages of the project are not indexed, in which case
+    /// the asset may be part of one of those
+    pub verified: bool,
+}
+
+impl DiagnosticResponse {
+    fn new(guid: String, value: Reference, verified: bool) -> Self {
+        let property = match value.kind {
+            ReferenceKind::Object { property } => property,
+            _ => None,
+        };
+
+        let kind = match property.as_deref() {
+            Some("m_Script") => DiagnosticKind::MissingScript,
+            _ => DiagnosticKind::MissingReference,
+        };
+
+        Self {
+            kind,
+            guid,
+            file: value.file.to_string_lossy().to_string(),
+            line: value.line,
+            asset: value.asset,
+            object: value.object,
+            package: value.package,
+            property,
+            verified,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn object_ref(property: Option<&str>) -> Reference {
+        Reference {
+            file: PathBuf::from("/project/Assets/Level.unity"),
+            line: Some(12),
+            asset: Some("Level".to_owned()),
+            object: Some(vec!["Canvas".to_owned()]),
+            package: None,
+            kind: ReferenceKind::Object {
+                property: property.map(str::to_owned),
+            },
+        }
+    }
+
+    #[test]
+    fn classifies_missing_scripts() {
+        let guid = "0123456789abcdef0123456789abcdef".to_owned();
+
+        let script = DiagnosticResponse::new(guid.clone(), object_ref(Some("m_Script")), true);
+        assert_eq!(script.kind, DiagnosticKind::MissingScript);
+
+        let material = DiagnosticResponse::new(
+            guid.clone(),
+            object_ref(Some("m_Materials.Array.data[0]")),
+            false,
+        );
+        assert_eq!(material.kind, DiagnosticKind::MissingReference);
+        assert_eq!(
+            material.property.as_deref(),
+            Some("m_Materials.Array.data[0]")
+        );
+        assert!(!material.verified);
+
+        let unknown = DiagnosticResponse::new(guid, object_ref(None), true);
+        assert_eq!(unknown.kind, DiagnosticKind::MissingReference);
+    }
+}
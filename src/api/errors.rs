@@ -0,0 +1,34 @@
+use std::sync::Arc;
+
+use jsonrpsee::types::Params;
+use jsonrpsee::{Extensions, ResponsePayload};
+use serde::{Deserialize, Serialize};
+
+use crate::crawler::{Crawler, FileError};
+
+pub async fn rpc_errors_handler(
+    _params: Params<'static>,
+    context: Arc<Crawler>,
+    _extensions: Extensions,
+) -> ResponsePayload<'static, Vec<ErrorResponse>> {
+    log::debug!("Handling errors request");
+
+    let errors = context.errors.read().await;
+
+    ResponsePayload::success(errors.iter().map(|e| e.clone().into()).collect())
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ErrorResponse {
+    pub file: String,
+    pub message: String,
+}
+
+impl From<FileError> for ErrorResponse {
+    fn from(value: FileError) -> Self {
+        Self {
+            file: value.file.to_string_lossy().to_string(),
+            message: value.message,
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use jsonrpsee::types::{ErrorObjectOwned, Params};
+use jsonrpsee::{Extensions, ResponsePayload};
+use serde::{Deserialize, Serialize};
+
+use crate::api::method::reference_count;
+use crate::crawler::{Crawler, MethodDefinition, Reference};
+
+const DEFAULT_PAGE_SIZE: usize = 100;
+
+pub async fn rpc_list_methods_handler(
+    params: Params<'static>,
+    context: Arc<Crawler>,
+    _extensions: Extensions,
+) -> ResponsePayload<'static, Page<TypeMethodsResponse>> {
+    log::debug!("Handling method list request");
+
+    let list: ListParam = match parse_optional(params) {
+        Ok(l) => l,
+        Err(e) => return ResponsePayload::error(e),
+    };
+
+    let types = group_by_type(&*context.method_refs.read().await, &list);
+
+    let grouped = types
+        .into_iter()
+        .map(
+            |((method_assembly, method_typename), methods)| TypeMethodsResponse {
+                method_assembly,
+                method_typename,
+                references: methods.values().sum(),
+                methods: methods
+                    .into_iter()
+                    .map(|(method_name, references)| MethodCountResponse {
+                        method_name,
+                        references,
+                    })
+                    .collect(),
+            },
+        );
+
+    ResponsePayload::success(Page::new(grouped, &list.page))
+}
+
+pub async fn rpc_list_types_handler(
+    params: Params<'static>,
+    context: Arc<Crawler>,
+    _extensions: Extensions,
+) -> ResponsePayload<'static, Page<TypeResponse>> {
+    log::debug!("Handling type list request");
+
+    let list: ListParam = match parse_optional(params) {
+        Ok(l) => l,
+        Err(e) => return ResponsePayload::error(e),
+    };
+
+    let types = group_by_type(&*context.method_refs.read().await, &list);
+
+    let grouped =
+        types.into_iter().map(
+            |((method_assembly, method_typename), methods)| TypeResponse {
+                method_assembly,
+                method_typename,
+                methods: methods.len(),
+                references: methods.values().sum(),
+            },
+        );
+
+    ResponsePayload::success(Page::new(grouped, &list.page))
+}
+
+/// Both methods can be called without any parameters to list everything
+fn parse_optional(params: Params<'static>) -> Result<ListParam, ErrorObjectOwned> {
+    if params.is_object() {
+        params.parse()
+    } else {
+        Ok(ListParam::default())
+    }
+}
+
+/// Counts the references of every method the way the `method` request finds
+/// them, grouped by assembly and type and sorted by name
+fn group_by_type(
+    method_refs: &HashMap<MethodDefinition, Vec<Reference>>,
+    list: &ListParam,
+) -> BTreeMap<(String, String), BTreeMap<String, usize>> {
+    let mut types: BTreeMap<(String, String), BTreeMap<String, usize>> = BTreeMap::new();
+
+    let matching = method_refs.keys().filter(|method| {
+        list.method_assembly
+            .as_ref()
+            .is_none_or(|assembly| *assembly == method.method_assembly)
+            && list
+                .method_typename
+                .as_ref()
+                .is_none_or(|typename| *typename == method.method_typename)
+    });
+
+    for method in matching {
+        *types
+            .entry((
+                method.method_assembly.clone(),
+                method.method_typename.clone(),
+            ))
+            .or_default()
+            .entry(method.method_name.clone())
+            .or_default() += reference_count(method_refs, method);
+    }
+
+    types
+}
+
+/// Optionally narrows the listing down to a single assembly or type
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ListParam {
+    pub method_assembly: Option<String>,
+    pub method_typename: Option<String>,
+
+    #[serde(flatten)]
+    pub page: PageParam,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PageParam {
+    #[serde(default)]
+    pub offset: usize,
+
+    /// Defaults to 100 items
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Page<T> {
+    /// The number of items on all pages together
+    pub total: usize,
+    pub offset: usize,
+    pub items: Vec<T>,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: impl ExactSizeIterator<Item = T>, page: &PageParam) -> Self {
+        Self {
+            total: items.len(),
+            offset: page.offset,
+            items: items
+                .skip(page.offset)
+                .take(page.limit.unwrap_or(DEFAULT_PAGE_SIZE))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TypeMethodsResponse {
+    pub method_assembly: String,
+    pub method_typename: String,
+
+    /// The total number of references to all methods of the type
+    pub references: usize,
+    pub methods: Vec<MethodCountResponse>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MethodCountResponse {
+    pub method_name: String,
+    pub references: usize,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TypeResponse {
+    pub method_assembly: String,
+    pub method_typename: String,
+
+    /// The number of referenced methods of the type
+    pub methods: usize,
+
+    /// The total number of references to all methods of the type
+    pub references: usize,
+}
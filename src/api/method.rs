@@ -1,11 +1,17 @@
 use core::fmt::Display;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
 use std::sync::Arc;
 
 use jsonrpsee::types::Params;
 use jsonrpsee::{Extensions, ResponsePayload};
 use serde::{Deserialize, Serialize};
 
-use crate::crawler::{Crawler, MethodDefinition, Reference};
+use crate::crawler::{
+    CallState, Crawler, ListenerMode, MethodDefinition, ObjectPointer, Reference, ReferenceKind,
+    StaticArgument,
+};
+use crate::metafile::AssetTable;
 
 pub async fn rpc_method_handler(
     params: Params<'static>,
@@ -19,22 +25,119 @@ pub async fn rpc_method_handler(
         Err(e) => return ResponsePayload::error(e),
     };
 
+    let method: MethodDefinition = method.into();
+
+    let all_refs = context.method_refs.read().await;
+    let assets = context.assets.read().await;
+
+    ResponsePayload::success(method_responses(&all_refs, &method, &assets))
+}
+
+pub async fn rpc_methods_batch_handler(
+    params: Params<'static>,
+    context: Arc<Crawler>,
+    _extensions: Extensions,
+) -> ResponsePayload<'static, BTreeMap<String, Vec<MethodResponse>>> {
+    log::debug!("Handling method batch request");
+
+    let batch: MethodsBatchParam = match params.parse() {
+        Ok(b) => b,
+        Err(e) => return ResponsePayload::error(e),
+    };
+
+    // Every method is looked up under the same lock, so the results are
+    // consistent with each other
     let all_refs = context.method_refs.read().await;
-    let method_refs = match all_refs.get(&method.into()) {
-        Some(r) => r,
-        None => return ResponsePayload::success(Vec::new()),
+    let assets = context.assets.read().await;
+
+    let methods: Vec<MethodDefinition> = match batch {
+        MethodsBatchParam::Methods(methods) => methods.into_iter().map(Into::into).collect(),
+        MethodsBatchParam::Type {
+            method_typename,
+            method_assembly,
+        } => all_refs
+            .keys()
+            .filter(|method| {
+                method.method_typename == method_typename
+                    && method_assembly
+                        .as_ref()
+                        .is_none_or(|assembly| *assembly == method.method_assembly)
+            })
+            .cloned()
+            .collect(),
     };
 
-    ResponsePayload::success(method_refs.iter().map(|r| r.clone().into()).collect())
+    let results = methods
+        .into_iter()
+        .map(|method| {
+            let responses = method_responses(&all_refs, &method, &assets);
+            (batch_key(&method), responses)
+        })
+        .collect();
+
+    ResponsePayload::success(results)
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq, Deserialize, Serialize)]
+/// Batch results are keyed the way Unity writes the target of a UnityEvent,
+/// followed by the method name (`Game.Menu.OnPlay, Assembly-CSharp`)
+fn batch_key(method: &MethodDefinition) -> String {
+    format!(
+        "{}.{}, {}",
+        method.method_typename, method.method_name, method.method_assembly
+    )
+}
+
+/// Collects the references to a method, including animation events calling a
+/// method with the same name
+pub fn method_responses(
+    all_refs: &HashMap<MethodDefinition, Vec<Reference>>,
+    method: &MethodDefinition,
+    assets: &AssetTable,
+) -> Vec<MethodResponse> {
+    method_references(all_refs, method)
+        .cloned()
+        .filter_map(|r| MethodResponse::new(r, assets))
+        .collect()
+}
+
+/// The number of references [`method_responses`] finds for a method
+pub fn reference_count(
+    all_refs: &HashMap<MethodDefinition, Vec<Reference>>,
+    method: &MethodDefinition,
+) -> usize {
+    method_references(all_refs, method).count()
+}
+
+fn method_references<'a>(
+    all_refs: &'a HashMap<MethodDefinition, Vec<Reference>>,
+    method: &MethodDefinition,
+) -> impl Iterator<Item = &'a Reference> {
+    // Animation events call any method with a matching name
+    let untyped = (!method.is_untyped())
+        .then(|| all_refs.get(&MethodDefinition::untyped(method.method_name.clone())))
+        .flatten();
+
+    all_refs.get(method).into_iter().chain(untyped).flatten()
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 pub struct MethodParam {
     pub method_name: String,
     pub method_assembly: String,
     pub method_typename: String,
 }
 
+/// Either a list of methods, or all methods of a type that have references
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum MethodsBatchParam {
+    Methods(Vec<MethodParam>),
+    Type {
+        method_typename: String,
+        method_assembly: Option<String>,
+    },
+}
+
 impl From<MethodParam> for MethodDefinition {
     fn from(value: MethodParam) -> Self {
         Self {
@@ -45,6 +148,16 @@ impl From<MethodParam> for MethodDefinition {
     }
 }
 
+impl From<MethodDefinition> for MethodParam {
+    fn from(value: MethodDefinition) -> Self {
+        Self {
+            method_name: value.method_name,
+            method_assembly: value.method_assembly,
+            method_typename: value.method_typename,
+        }
+    }
+}
+
 impl Display for MethodParam {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -58,12 +171,113 @@ impl Display for MethodParam {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct MethodResponse {
     pub file: String,
+    pub line: Option<usize>,
+    pub asset: Option<String>,
+    pub object: Option<Vec<String>>,
+    pub package: Option<String>,
+    #[serde(flatten)]
+    pub kind: MethodReferenceKind,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MethodReferenceKind {
+    UnityEvent {
+        target: Option<ObjectPointerResponse>,
+        mode: Option<ListenerMode>,
+        argument: Option<ArgumentResponse>,
+        call_state: Option<CallState>,
+    },
+    AnimationEvent {
+        clip: String,
+        time: f64,
+    },
 }
 
-impl From<Reference> for MethodResponse {
-    fn from(value: Reference) -> Self {
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArgumentResponse {
+    Object {
+        object: Option<ObjectPointerResponse>,
+        type_name: Option<String>,
+    },
+    Int(i64),
+    Float(f64),
+    String(String),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ObjectPointerResponse {
+    pub file_id: i64,
+    pub guid: Option<String>,
+
+    /// The file containing the object, if it is known
+    pub path: Option<String>,
+    pub object: Option<Vec<String>>,
+}
+
+impl ObjectPointerResponse {
+    /// Objects without a GUID are in the referencing file itself, others are
+    /// looked up in the asset table
+    fn new(value: ObjectPointer, file: &Path, assets: &AssetTable) -> Self {
+        let path = match &value.guid {
+            Some(guid) => assets.path(guid).map(Path::to_path_buf),
+            None => Some(file.to_path_buf()),
+        };
+
         Self {
-            file: value.file.to_string_lossy().to_string(),
+            file_id: value.file_id,
+            guid: value.guid,
+            path: path.map(|p| p.to_string_lossy().to_string()),
+            object: value.object,
         }
     }
 }
+
+impl MethodReferenceKind {
+    /// Returns `None` for references that do not call a method. Those are
+    /// never stored as method references.
+    fn new(value: ReferenceKind, file: &Path, assets: &AssetTable) -> Option<Self> {
+        let call = match value {
+            ReferenceKind::AnimationEvent { clip, time } => {
+                return Some(Self::AnimationEvent { clip, time })
+            }
+            ReferenceKind::UnityEvent(call) => *call,
+            ReferenceKind::Object { .. } => return None,
+        };
+
+        let argument = call.argument.map(|argument| match argument {
+            StaticArgument::Object { object, type_name } => ArgumentResponse::Object {
+                object: object.map(|o| ObjectPointerResponse::new(o, file, assets)),
+                type_name,
+            },
+            StaticArgument::Int(i) => ArgumentResponse::Int(i),
+            StaticArgument::Float(f) => ArgumentResponse::Float(f),
+            StaticArgument::String(s) => ArgumentResponse::String(s),
+            StaticArgument::Bool(b) => ArgumentResponse::Bool(b),
+        });
+
+        Some(Self::UnityEvent {
+            target: call
+                .target
+                .map(|t| ObjectPointerResponse::new(t, file, assets)),
+            mode: call.mode,
+            argument,
+            call_state: call.call_state,
+        })
+    }
+}
+
+impl MethodResponse {
+    pub fn new(value: Reference, assets: &AssetTable) -> Option<Self> {
+        Some(Self {
+            kind: MethodReferenceKind::new(value.kind, &value.file, assets)?,
+            file: value.file.to_string_lossy().to_string(),
+            line: value.line,
+            asset: value.asset,
+            object: value.object,
+            package: value.package,
+        })
+    }
+}
@@ -1,2 +1,13 @@
+pub mod asset;
+pub mod component;
+pub mod dependencies;
+pub mod diagnostics;
+pub mod errors;
+pub mod list;
 pub mod method;
+pub mod object;
+pub mod prefab;
+pub mod search;
 pub mod status;
+pub mod subscription;
+pub mod unused;
@@ -0,0 +1,93 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use jsonrpsee::types::error::INVALID_PARAMS_CODE;
+use jsonrpsee::types::{ErrorObject, ErrorObjectOwned, Params};
+use jsonrpsee::{Extensions, ResponsePayload};
+use serde::{Deserialize, Serialize};
+
+use crate::crawler::{Crawler, ObjectDefinition, Reference, ReferenceKind};
+
+pub async fn rpc_object_handler(
+    params: Params<'static>,
+    context: Arc<Crawler>,
+    _extensions: Extensions,
+) -> ResponsePayload<'static, Vec<ObjectResponse>> {
+    log::debug!("Handling object request");
+
+    let object: ObjectParam = match params.parse() {
+        Ok(o) => o,
+        Err(e) => return ResponsePayload::error(e),
+    };
+
+    let guid = match object {
+        ObjectParam::Guid { guid } => guid,
+        ObjectParam::Path { path } => match guid_for_path(&context, &path).await {
+            Ok(guid) => guid,
+            Err(e) => return ResponsePayload::error(e),
+        },
+    };
+
+    let all_refs = context.object_refs.read().await;
+    let object_refs = match all_refs.get(&ObjectDefinition { guid }) {
+        Some(r) => r,
+        None => return ResponsePayload::success(Vec::new()),
+    };
+
+    ResponsePayload::success(object_refs.iter().map(|r| r.clone().into()).collect())
+}
+
+/// Resolves a path given by a client to the GUID of the asset there
+pub async fn guid_for_path(context: &Crawler, path: &str) -> Result<String, ErrorObjectOwned> {
+    let asset = context.resolve_path(Path::new(path));
+
+    match context.asset_guid(&asset).await {
+        Ok(Some(guid)) => Ok(guid),
+        Ok(None) => Err(ErrorObject::owned(
+            INVALID_PARAMS_CODE,
+            format!("No GUID found in the .meta file of {}", path),
+            None::<()>,
+        )),
+        Err(e) => Err(ErrorObject::owned(
+            INVALID_PARAMS_CODE,
+            format!("Could not read the .meta file of {}: {}", path, e),
+            None::<()>,
+        )),
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ObjectParam {
+    Guid { guid: String },
+    Path { path: String },
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ObjectResponse {
+    pub file: String,
+    pub line: Option<usize>,
+    pub asset: Option<String>,
+    pub object: Option<Vec<String>>,
+    pub package: Option<String>,
+
+    /// The serialized property holding the reference
+    /// (`m_Materials.Array.data[0]`)
+    pub property: Option<String>,
+}
+
+impl From<Reference> for ObjectResponse {
+    fn from(value: Reference) -> Self {
+        Self {
+            file: value.file.to_string_lossy().to_string(),
+            line: value.line,
+            asset: value.asset,
+            object: value.object,
+            package: value.package,
+            property: match value.kind {
+                ReferenceKind::Object { property } => property,
+                _ => None,
+            },
+        }
+    }
+}
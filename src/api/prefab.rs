@@ -0,0 +1,91 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+
+use jsonrpsee::types::Params;
+use jsonrpsee::{Extensions, ResponsePayload};
+use serde::{Deserialize, Serialize};
+
+use crate::api::object::{guid_for_path, ObjectParam};
+use crate::crawler::{Crawler, ObjectDefinition, Reference};
+
+pub async fn rpc_prefab_instances_handler(
+    params: Params<'static>,
+    context: Arc<Crawler>,
+    _extensions: Extensions,
+) -> ResponsePayload<'static, Vec<PrefabInstanceResponse>> {
+    log::debug!("Handling prefab instances request");
+
+    let prefab: ObjectParam = match params.parse() {
+        Ok(p) => p,
+        Err(e) => return ResponsePayload::error(e),
+    };
+
+    let guid = match prefab {
+        ObjectParam::Guid { guid } => guid,
+        ObjectParam::Path { path } => match guid_for_path(&context, &path).await {
+            Ok(guid) => guid,
+            Err(e) => return ResponsePayload::error(e),
+        },
+    };
+
+    let instance_refs = context.instance_refs.read().await;
+    let assets = context.assets.read().await;
+
+    let mut found = Vec::new();
+    let mut visited = HashSet::from([guid.clone()]);
+
+    // Prefabs to find the instances of, with the prefabs they are nested in
+    let mut queue = VecDeque::from([(guid, Vec::new())]);
+
+    while let Some((prefab, via)) = queue.pop_front() {
+        let Some(prefab_refs) = instance_refs.get(&ObjectDefinition { guid: prefab }) else {
+            continue;
+        };
+
+        for r in prefab_refs {
+            found.push(PrefabInstanceResponse::new(r.clone(), via.clone()));
+
+            // An instance within another prefab, or a variant, is placed
+            // wherever that prefab is instantiated as well
+            let Some(parent) = assets.guid(&r.file) else {
+                continue;
+            };
+
+            if visited.insert(parent.to_owned()) {
+                let mut parent_via = via.clone();
+                parent_via.push(r.file.to_string_lossy().to_string());
+
+                queue.push_back((parent.to_owned(), parent_via));
+            }
+        }
+    }
+
+    ResponsePayload::success(found)
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PrefabInstanceResponse {
+    pub file: String,
+    pub line: Option<usize>,
+    pub asset: Option<String>,
+    pub object: Option<Vec<String>>,
+    pub package: Option<String>,
+
+    /// The prefabs and variants the requested prefab is nested in to end up
+    /// in this instance, starting with the innermost one. Empty for instances
+    /// of the requested prefab itself.
+    pub via: Vec<String>,
+}
+
+impl PrefabInstanceResponse {
+    fn new(value: Reference, via: Vec<String>) -> Self {
+        Self {
+            file: value.file.to_string_lossy().to_string(),
+            line: value.line,
+            asset: value.asset,
+            object: value.object,
+            package: value.package,
+            via,
+        }
+    }
+}
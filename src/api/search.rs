@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use jsonrpsee::types::error::INVALID_PARAMS_CODE;
+use jsonrpsee::types::{ErrorObject, ErrorObjectOwned, Params};
+use jsonrpsee::{Extensions, ResponsePayload};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::api::method::{reference_count, MethodParam};
+use crate::crawler::Crawler;
+
+pub async fn rpc_search_methods_handler(
+    params: Params<'static>,
+    context: Arc<Crawler>,
+    _extensions: Extensions,
+) -> ResponsePayload<'static, Vec<MethodSearchResponse>> {
+    log::debug!("Handling method search request");
+
+    let search: SearchMethodsParam = match params.parse() {
+        Ok(s) => s,
+        Err(e) => return ResponsePayload::error(e),
+    };
+
+    let matcher = |pattern: &Option<String>| {
+        pattern
+            .as_deref()
+            .map(|p| search_pattern(p, search.match_mode, search.case_insensitive))
+            .transpose()
+    };
+
+    let (name, assembly, typename) = match (
+        matcher(&search.method_name),
+        matcher(&search.method_assembly),
+        matcher(&search.method_typename),
+    ) {
+        (Ok(name), Ok(assembly), Ok(typename)) => (name, assembly, typename),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => return ResponsePayload::error(e),
+    };
+
+    let is_match =
+        |pattern: &Option<Regex>, value: &str| pattern.as_ref().is_none_or(|p| p.is_match(value));
+
+    let method_refs = context.method_refs.read().await;
+
+    let mut found: Vec<MethodSearchResponse> = method_refs
+        .keys()
+        .filter(|method| {
+            is_match(&name, &method.method_name)
+                && is_match(&assembly, &method.method_assembly)
+                && is_match(&typename, &method.method_typename)
+        })
+        .map(|method| MethodSearchResponse {
+            method: method.clone().into(),
+            references: reference_count(&method_refs, method),
+        })
+        .collect();
+
+    drop(method_refs);
+
+    found.sort_by(|a, b| a.method.cmp(&b.method));
+
+    ResponsePayload::success(found)
+}
+
+/// Builds the regex matching a search pattern. Every mode other than `regex`
+/// matches the pattern literally.
+pub fn search_pattern(
+    pattern: &str,
+    mode: MatchMode,
+    case_insensitive: bool,
+) -> Result<Regex, ErrorObjectOwned> {
+    let regex = match mode {
+        MatchMode::Exact => format!("^{}$", regex::escape(pattern)),
+        MatchMode::Prefix => format!("^{}", regex::escape(pattern)),
+        MatchMode::Contains => regex::escape(pattern),
+        MatchMode::Regex => pattern.to_owned(),
+    };
+
+    RegexBuilder::new(&regex)
+        .case_insensitive(case_insensitive)
+        .build()
+        .map_err(|e| {
+            ErrorObject::owned(
+                INVALID_PARAMS_CODE,
+                format!("Invalid search pattern {}: {}", pattern, e),
+                None::<()>,
+            )
+        })
+}
+
+/// Every field is optional, fields that are left out match anything
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SearchMethodsParam {
+    pub method_name: Option<String>,
+    pub method_assembly: Option<String>,
+    pub method_typename: Option<String>,
+
+    #[serde(default)]
+    pub match_mode: MatchMode,
+
+    #[serde(default)]
+    pub case_insensitive: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMode {
+    Exact,
+    Prefix,
+    #[default]
+    Contains,
+    Regex,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MethodSearchResponse {
+    #[serde(flatten)]
+    pub method: MethodParam,
+
+    /// How many references to the method there are, including animation
+    /// events calling a method with the same name
+    pub references: usize,
+}
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
-use jsonrpsee::types::Params;
+use jsonrpsee::types::error::CALL_EXECUTION_FAILED_CODE;
+use jsonrpsee::types::{ErrorObject, ErrorObjectOwned, Params};
 use jsonrpsee::{Extensions, ResponsePayload};
 use serde::{Deserialize, Serialize};
 
@@ -13,13 +14,70 @@ pub async fn rpc_status_handler(
 ) -> ResponsePayload<'static, StatusResponse> {
     log::debug!("Handling status request");
 
-    ResponsePayload::success(context.status.read().await.clone())
+    ResponsePayload::success(current_status(&context).await)
 }
 
+pub async fn rpc_retry_handler(
+    _params: Params<'static>,
+    context: Arc<Crawler>,
+    _extensions: Extensions,
+) -> ResponsePayload<'static, StatusResponse> {
+    log::debug!("Handling retry request");
+
+    context.restart().await;
+
+    ResponsePayload::success(current_status(&context).await)
+}
+
+pub async fn current_status(context: &Crawler) -> StatusResponse {
+    let mut status = context.status.read().await.clone();
+
+    if let StatusResponse::Initializing { progress } = &mut status {
+        *progress = context.progress.snapshot();
+    }
+
+    status
+}
+
+/// Fails for requests that only give correct results once the whole project
+/// has been indexed
+pub async fn require_ready(context: &Crawler) -> Result<(), ErrorObjectOwned> {
+    match *context.status.read().await {
+        StatusResponse::Ready => Ok(()),
+        _ => Err(ErrorObject::owned(
+            CALL_EXECUTION_FAILED_CODE,
+            "The project has not been fully indexed yet",
+            None::<()>,
+        )),
+    }
+}
+
+/// Serialized as an object whose `state` is the lowercase variant name, along
+/// with the fields of the variant
 #[derive(Serialize, Deserialize, Clone, Debug)]
-#[serde(rename_all = "snake_case")]
+#[serde(tag = "state", rename_all = "snake_case")]
 pub enum StatusResponse {
     Inactive,
-    Initializing,
+    Initializing {
+        progress: CrawlProgress,
+    },
     Ready,
+    Error {
+        message: String,
+        path: Option<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct CrawlProgress {
+    pub discovered_files: u64,
+    pub processed_files: u64,
+    pub discovered_bytes: u64,
+    pub processed_bytes: u64,
+
+    /// Time since the crawl started, in seconds
+    pub elapsed: f64,
+
+    /// Estimated time until the crawl is done, in seconds
+    pub eta: Option<f64>,
 }
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use jsonrpsee::core::SubscriptionResult;
+use jsonrpsee::types::Params;
+use jsonrpsee::{Extensions, PendingSubscriptionSink, SubscriptionMessage, SubscriptionSink};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+
+use crate::api::method::MethodParam;
+use crate::api::status::current_status;
+use crate::crawler::{Crawler, ReferenceChange};
+
+pub async fn rpc_subscribe_status_handler(
+    _params: Params<'static>,
+    pending: PendingSubscriptionSink,
+    context: Arc<Crawler>,
+    _extensions: Extensions,
+) -> SubscriptionResult {
+    log::debug!("Handling status subscription");
+
+    // Subscribe before reading the current status, so no transitions are missed
+    let events = context.status_events.subscribe();
+
+    let sink = pending.accept().await?;
+
+    sink.send(SubscriptionMessage::from_json(
+        &current_status(&context).await,
+    )?)
+    .await?;
+
+    forward_events(sink, events, |status| status).await
+}
+
+pub async fn rpc_subscribe_references_handler(
+    _params: Params<'static>,
+    pending: PendingSubscriptionSink,
+    context: Arc<Crawler>,
+    _extensions: Extensions,
+) -> SubscriptionResult {
+    log::debug!("Handling references subscription");
+
+    let events = context.reference_events.subscribe();
+
+    let sink = pending.accept().await?;
+
+    forward_events(sink, events, ReferencesChangedNotification::from).await
+}
+
+/// Sends every event from the crawler to the subscriber, until either side
+/// closes
+async fn forward_events<T: Clone, N: Serialize>(
+    sink: SubscriptionSink,
+    mut events: Receiver<T>,
+    to_notification: impl Fn(T) -> N,
+) -> SubscriptionResult {
+    loop {
+        tokio::select! {
+            _ = sink.closed() => return Ok(()),
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let msg = SubscriptionMessage::from_json(&to_notification(event))?;
+                    sink.send(msg).await?;
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    log::warn!("Subscriber fell behind, {} notifications were dropped", missed);
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReferencesChangedNotification {
+    pub file: String,
+    pub methods: Vec<MethodParam>,
+    pub guids: Vec<String>,
+}
+
+impl From<ReferenceChange> for ReferencesChangedNotification {
+    fn from(value: ReferenceChange) -> Self {
+        Self {
+            file: value.file.to_string_lossy().to_string(),
+            methods: value.methods.into_iter().map(MethodParam::from).collect(),
+            guids: value.objects.into_iter().map(|o| o.guid).collect(),
+        }
+    }
+}
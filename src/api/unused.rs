@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use jsonrpsee::types::Params;
+use jsonrpsee::{Extensions, ResponsePayload};
+use serde::{Deserialize, Serialize};
+
+use crate::api::status::require_ready;
+use crate::crawler::Crawler;
+use crate::unused::{find_unused_assets, UnusedAsset, UnusedAssets};
+
+pub async fn rpc_unused_assets_handler(
+    _params: Params<'static>,
+    context: Arc<Crawler>,
+    _extensions: Extensions,
+) -> ResponsePayload<'static, UnusedAssetsResponse> {
+    log::debug!("Handling unused assets request");
+
+    // Anything not indexed yet would show up as unused
+    if let Err(e) = require_ready(&context).await {
+        return ResponsePayload::error(e);
+    }
+
+    ResponsePayload::success(find_unused_assets(&context).await.into())
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UnusedAssetsResponse {
+    pub unused: Vec<UnusedAssetResponse>,
+
+    /// Assets that nothing known references, while some of the assets that
+    /// can be reached could not be read
+    pub unknown: Vec<UnusedAssetResponse>,
+
+    /// The assets that can be reached but could not be read, as they are
+    /// serialized in Unity's binary format
+    pub unparsed: Vec<UnusedAssetResponse>,
+}
+
+impl From<UnusedAssets> for UnusedAssetsResponse {
+    fn from(value: UnusedAssets) -> Self {
+        let convert = |assets: Vec<UnusedAsset>| assets.into_iter().map(Into::into).collect();
+
+        Self {
+            unused: convert(value.unused),
+            unknown: convert(value.unknown),
+            unparsed: convert(value.unparsed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UnusedAssetResponse {
+    pub guid: String,
+    pub path: String,
+}
+
+impl From<UnusedAsset> for UnusedAssetResponse {
+    fn from(value: UnusedAsset) -> Self {
+        Self {
+            guid: value.guid,
+            path: value.path.to_string_lossy().to_string(),
+        }
+    }
+}
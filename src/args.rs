@@ -3,6 +3,8 @@ use std::path::PathBuf;
 use clap::{Parser, ValueEnum};
 use log::LevelFilter;
 
+use crate::crawler::DEFAULT_EXTENSIONS;
+
 #[derive(Parser, Debug)]
 #[command(version)]
 pub struct CliArgs {
@@ -18,6 +20,48 @@ pub struct CliArgs {
     #[arg(long, default_value = "false")]
     pub json_logs: bool,
 
+    /// How long a changed file must be left alone before it is re-indexed, in
+    /// milliseconds
+    #[arg(long, default_value = "250")]
+    pub debounce_ms: u64,
+
+    /// The extensions of the text-serialized Unity files to index
+    #[arg(long, value_delimiter = ',', default_value = DEFAULT_EXTENSIONS)]
+    pub extensions: Vec<String>,
+
+    /// Extra paths to skip while crawling and watching, in `.gitignore` syntax
+    /// relative to the Unity project. `Library`, `Temp`, `Logs`, `obj`,
+    /// `UserSettings` and build folders are always skipped.
+    #[arg(long, value_delimiter = ',')]
+    pub exclude: Vec<String>,
+
+    /// Paths to crawl and watch even if they are excluded, in `.gitignore`
+    /// syntax relative to the Unity project
+    #[arg(long, value_delimiter = ',')]
+    pub include: Vec<String>,
+
+    /// Do not skip the paths ignored by the `.gitignore` of the project
+    #[arg(long, default_value = "false")]
+    pub no_gitignore: bool,
+
+    /// Only index the given folder, without the packages of the Unity project
+    #[arg(long, default_value = "false")]
+    pub no_packages: bool,
+
+    /// Where to store the index cache. Defaults to the `Library` folder of the
+    /// Unity project
+    #[arg(long)]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Always index the entire project, without reading or writing a cache
+    #[arg(long, default_value = "false")]
+    pub no_cache: bool,
+
+    /// Index the folder, print the assets that nothing reachable from the build
+    /// references and exit without starting the server
+    #[arg(long, default_value = "false")]
+    pub report_unused: bool,
+
     /// The verbosity of the logger
     #[cfg(not(debug_assertions))]
     #[arg(value_enum, short, long, default_value_t = LogLevel::Warn)]
@@ -0,0 +1,522 @@
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use tokio::io;
+
+use crate::crawler::{ArcRefSet, MethodDefinition, ObjectDefinition, Reference};
+use crate::metafile::{asset_path, meta_path};
+
+const CACHE_FILE_NAME: &str = "unity-reference-server-cache.json";
+
+/// The version of the server writing the cache
+const CACHE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Bumped whenever what gets indexed, or how it is stored, changes. Caches
+/// written with any other format are never used.
+const CACHE_FORMAT_VERSION: u32 = 8;
+
+/// Picks where the cache for the given folder is stored. Without an explicit
+/// cache folder, the cache is stored in the `Library` folder of the Unity
+/// project, if it can be found.
+pub fn cache_file_path(dir: &Path, cache_dir: Option<&Path>) -> Option<PathBuf> {
+    if let Some(cache_dir) = cache_dir {
+        return Some(cache_dir.join(CACHE_FILE_NAME));
+    }
+
+    [Some(dir), dir.parent()]
+        .into_iter()
+        .flatten()
+        .map(|root| root.join("Library"))
+        .find(|library| library.is_dir())
+        .map(|library| library.join(CACHE_FILE_NAME))
+}
+
+/// Identifies the exact contents of a file at the time it was indexed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    modified: Option<SystemTime>,
+    size: u64,
+    hash: u64,
+}
+
+impl FileStamp {
+    async fn of_file(file: &Path, meta: &Metadata) -> io::Result<Self> {
+        let content = tokio::fs::read(file).await?;
+
+        Ok(Self {
+            modified: meta.modified().ok(),
+            size: meta.len(),
+            hash: fnv1a(&content),
+        })
+    }
+}
+
+/// 64-bit FNV-1a, which is stable across Rust versions unlike the hashers in
+/// the standard library
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Everything the index contained for a single file when the cache was written
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CachedFile {
+    stamp: Option<FileStamp>,
+    methods: Vec<(MethodDefinition, Reference)>,
+    objects: Vec<(ObjectDefinition, Reference)>,
+    components: Vec<(ObjectDefinition, Reference)>,
+    instances: Vec<(ObjectDefinition, Reference)>,
+    guid: Option<String>,
+    folder: bool,
+    class: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IndexCache {
+    version: String,
+
+    /// Caches written before the format was versioned have none
+    #[serde(default)]
+    format: u32,
+
+    dir: PathBuf,
+    files: HashMap<PathBuf, CachedFile>,
+}
+
+/// The cache as used during a single crawl. Unchanged files are restored from
+/// the previous cache instead of being parsed, and the stamps of all files are
+/// collected for writing the next cache.
+#[derive(Debug, Default)]
+pub struct CrawlCache {
+    previous: HashMap<PathBuf, CachedFile>,
+    stamps: Mutex<HashMap<PathBuf, FileStamp>>,
+}
+
+impl CrawlCache {
+    /// Loads the cache at the given path. A missing, unreadable or outdated
+    /// cache results in an empty one, causing every file to be parsed.
+    pub async fn load(cache_file: &Path, dir: &Path) -> Self {
+        let content = match tokio::fs::read(cache_file).await {
+            Ok(c) => c,
+            Err(e) => {
+                if e.kind() != io::ErrorKind::NotFound {
+                    log::warn!("Could not read index cache: {}", e);
+                }
+
+                return Self::default();
+            }
+        };
+
+        let cache: IndexCache = match serde_json::from_slice(&content) {
+            Ok(c) => c,
+            Err(e) => {
+                log::warn!("Could not parse index cache, ignoring it: {}", e);
+                return Self::default();
+            }
+        };
+
+        if cache.version != CACHE_VERSION
+            || cache.format != CACHE_FORMAT_VERSION
+            || cache.dir != dir
+        {
+            log::info!("Index cache was written for another version or folder, ignoring it");
+            return Self::default();
+        }
+
+        log::info!("Loaded index cache with {} files", cache.files.len());
+
+        Self {
+            previous: cache.files,
+            stamps: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Restores the index entries of a file if it did not change since the
+    /// cache was written, returning whether it did. Either way, the current
+    /// stamp of the file is recorded for the next cache.
+    pub async fn restore(&self, file: &Path, meta: &Metadata, refs: &ArcRefSet) -> bool {
+        let cached = self.previous.get(file);
+        let cached_stamp = cached.and_then(|c| c.stamp.as_ref());
+
+        let stamp = match cached_stamp {
+            // Checking the modification time and size avoids reading the file
+            Some(stamp) if stamp.modified == meta.modified().ok() && stamp.size == meta.len() => {
+                stamp.clone()
+            }
+            _ => match FileStamp::of_file(file, meta).await {
+                Ok(stamp) => stamp,
+                Err(e) => {
+                    log::debug!("Could not stamp {}: {}", file.to_string_lossy(), e);
+                    return false;
+                }
+            },
+        };
+
+        let unchanged = cached_stamp.is_some_and(|cached_stamp| cached_stamp.hash == stamp.hash);
+
+        self.stamps
+            .lock()
+            .unwrap()
+            .insert(file.to_path_buf(), stamp);
+
+        match cached {
+            Some(cached) if unchanged => {
+                log::trace!("Restoring {} from cache", file.to_string_lossy());
+                restore_file(file, cached, refs).await;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Leaves a file out of the next cache, so it is parsed again on the next
+    /// start. For files whose index entries depend on the contents of other
+    /// files in ways the entries themselves do not show.
+    pub fn leave_out(&self, file: &Path) {
+        self.stamps.lock().unwrap().remove(file);
+    }
+
+    /// Writes the current contents of the index to the cache file
+    pub async fn save(&self, cache_file: &Path, dir: &Path, refs: &ArcRefSet) -> io::Result<()> {
+        let mut files: HashMap<PathBuf, CachedFile> = self
+            .stamps
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|(file, stamp)| {
+                let cached = CachedFile {
+                    stamp: Some(stamp),
+                    ..Default::default()
+                };
+
+                (file, cached)
+            })
+            .collect();
+
+        // Files that failed to index are left out, so they are retried and
+        // their errors reported again on the next start
+        for error in refs.errors.read().await.iter() {
+            files.remove(&error.file);
+        }
+
+        // References completed through other files, such as calls whose type
+        // was found through the script of their target, go stale when those
+        // files change. Their files are parsed again on every start instead.
+        for method_refs in refs.methods.read().await.values() {
+            for r in method_refs.iter().filter(|r| r.depends_on_other_files()) {
+                files.remove(&r.file);
+            }
+        }
+
+        for (method, method_refs) in refs.methods.read().await.iter() {
+            for r in method_refs {
+                if let Some(cached) = files.get_mut(&r.file) {
+                    cached.methods.push((method.clone(), r.clone()));
+                }
+            }
+        }
+
+        for (object, object_refs) in refs.objects.read().await.iter() {
+            for r in object_refs {
+                if let Some(cached) = files.get_mut(&r.file) {
+                    cached.objects.push((object.clone(), r.clone()));
+                }
+            }
+        }
+
+        for (script, component_refs) in refs.components.read().await.iter() {
+            for r in component_refs {
+                if let Some(cached) = files.get_mut(&r.file) {
+                    cached.components.push((script.clone(), r.clone()));
+                }
+            }
+        }
+
+        for (prefab, instance_refs) in refs.instances.read().await.iter() {
+            for r in instance_refs {
+                if let Some(cached) = files.get_mut(&r.file) {
+                    cached.instances.push((prefab.clone(), r.clone()));
+                }
+            }
+        }
+
+        let assets = refs.assets.read().await;
+
+        for (guid, asset) in assets.iter() {
+            if let Some(cached) = files.get_mut(&meta_path(asset)) {
+                cached.guid = Some(guid.to_owned());
+                cached.folder = assets.is_folder(guid);
+            }
+        }
+
+        drop(assets);
+
+        for (script, class) in refs.scripts.read().await.iter() {
+            if let Some(cached) = files.get_mut(script) {
+                cached.class = Some(class.clone());
+            }
+        }
+
+        let cache = IndexCache {
+            version: CACHE_VERSION.to_owned(),
+            format: CACHE_FORMAT_VERSION,
+            dir: dir.to_path_buf(),
+            files,
+        };
+
+        let serialized = serde_json::to_vec(&cache)?;
+
+        // Write to a temporary file first, so a crash never leaves a half
+        // written cache behind
+        let tmp_file = cache_file.with_extension("tmp");
+        tokio::fs::write(&tmp_file, serialized).await?;
+        tokio::fs::rename(&tmp_file, cache_file).await?;
+
+        log::info!(
+            "Wrote index cache with {} files to {}",
+            cache.files.len(),
+            cache_file.to_string_lossy()
+        );
+
+        Ok(())
+    }
+}
+
+async fn restore_file(file: &Path, cached: &CachedFile, refs: &ArcRefSet) {
+    if !cached.methods.is_empty() {
+        let mut methods = refs.methods.write().await;
+
+        for (method, r) in &cached.methods {
+            methods.entry(method.clone()).or_default().push(r.clone());
+        }
+    }
+
+    if !cached.objects.is_empty() {
+        let mut objects = refs.objects.write().await;
+
+        for (object, r) in &cached.objects {
+            objects.entry(object.clone()).or_default().push(r.clone());
+        }
+    }
+
+    if !cached.components.is_empty() {
+        let mut components = refs.components.write().await;
+
+        for (script, r) in &cached.components {
+            components
+                .entry(script.clone())
+                .or_default()
+                .push(r.clone());
+        }
+    }
+
+    if !cached.instances.is_empty() {
+        let mut instances = refs.instances.write().await;
+
+        for (prefab, r) in &cached.instances {
+            instances.entry(prefab.clone()).or_default().push(r.clone());
+        }
+    }
+
+    if let Some(guid) = &cached.guid {
+        refs.assets
+            .write()
+            .await
+            .insert(guid.clone(), asset_path(file), cached.folder);
+    }
+
+    if let Some(class) = &cached.class {
+        refs.scripts
+            .write()
+            .await
+            .insert(file.to_path_buf(), class.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crawler::tests::{call_ref, test_dir, test_refset};
+    use crate::crawler::FileError;
+
+    fn write_file(file: &Path, content: &str) -> Metadata {
+        std::fs::write(file, content).unwrap();
+        std::fs::metadata(file).unwrap()
+    }
+
+    /// Indexes the given files into a fresh cache the way a crawl would,
+    /// without parsing any of them, and writes it
+    async fn save_cache(dir: &Path, files: &[&Path], refs: &ArcRefSet) -> PathBuf {
+        let cache = CrawlCache::default();
+
+        for file in files {
+            let meta = std::fs::metadata(file).unwrap();
+            assert!(!cache.restore(file, &meta, refs).await);
+        }
+
+        let cache_file = dir.join(CACHE_FILE_NAME);
+        cache.save(&cache_file, dir, refs).await.unwrap();
+        cache_file
+    }
+
+    fn level_method() -> MethodDefinition {
+        MethodDefinition {
+            method_name: "OnPlay".to_owned(),
+            method_assembly: "Assembly-CSharp".to_owned(),
+            method_typename: "Game.Menu".to_owned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn restores_unchanged_files() {
+        let dir = test_dir("restores-unchanged-files");
+        let level = dir.join("Level.unity");
+        let script = dir.join("Menu.cs");
+        let meta = dir.join("Menu.cs.meta");
+
+        write_file(&level, "level");
+        write_file(&script, "class Menu {}");
+        write_file(&meta, "guid: abc");
+
+        let refs = test_refset();
+
+        refs.methods.write().await.insert(
+            level_method(),
+            vec![Reference {
+                file: level.clone(),
+                ..call_ref(None)
+            }],
+        );
+        refs.assets
+            .write()
+            .await
+            .insert("abc".to_owned(), script.clone(), false);
+        refs.scripts
+            .write()
+            .await
+            .insert(script.clone(), "Game.Menu".to_owned());
+
+        let cache_file = save_cache(&dir, &[&level, &script, &meta], &refs).await;
+
+        let cache = CrawlCache::load(&cache_file, &dir).await;
+        let restored = test_refset();
+
+        for file in [&level, &script, &meta] {
+            let file_meta = std::fs::metadata(file).unwrap();
+            assert!(cache.restore(file, &file_meta, &restored).await);
+        }
+
+        let methods = restored.methods.read().await;
+        assert_eq!(methods[&level_method()].len(), 1);
+        assert_eq!(methods[&level_method()][0].file, level);
+        assert_eq!(
+            restored.assets.read().await.path("abc"),
+            Some(script.as_path())
+        );
+        assert_eq!(restored.scripts.read().await[&script], "Game.Menu");
+
+        _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn compares_contents_when_the_modification_time_changed() {
+        let dir = test_dir("compares-contents");
+        let touched = dir.join("Touched.unity");
+        let edited = dir.join("Edited.unity");
+
+        write_file(&touched, "level");
+        write_file(&edited, "level");
+
+        let cache_file = save_cache(&dir, &[&touched, &edited], &test_refset()).await;
+        let cache = CrawlCache::load(&cache_file, &dir).await;
+        let refs = test_refset();
+
+        let later = SystemTime::now() + std::time::Duration::from_secs(60);
+
+        std::fs::File::options()
+            .write(true)
+            .open(&touched)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+
+        let touched_meta = std::fs::metadata(&touched).unwrap();
+        assert!(cache.restore(&touched, &touched_meta, &refs).await);
+
+        // Same size, different contents
+        let edited_meta = write_file(&edited, "LEVEL");
+        assert!(!cache.restore(&edited, &edited_meta, &refs).await);
+
+        _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn ignores_caches_of_other_formats() {
+        let dir = test_dir("ignores-other-formats");
+        let level = dir.join("Level.unity");
+
+        write_file(&level, "level");
+
+        let cache_file = save_cache(&dir, &[&level], &test_refset()).await;
+
+        let mut written: IndexCache =
+            serde_json::from_slice(&std::fs::read(&cache_file).unwrap()).unwrap();
+        written.format = CACHE_FORMAT_VERSION - 1;
+        std::fs::write(&cache_file, serde_json::to_vec(&written).unwrap()).unwrap();
+
+        let cache = CrawlCache::load(&cache_file, &dir).await;
+        let meta = std::fs::metadata(&level).unwrap();
+        assert!(!cache.restore(&level, &meta, &test_refset()).await);
+
+        _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn leaves_out_files_that_depend_on_others_or_failed() {
+        let dir = test_dir("leaves-out-files");
+        let resolved = dir.join("Resolved.unity");
+        let failed = dir.join("Failed.unity");
+        let plain = dir.join("Plain.unity");
+
+        for file in [&resolved, &failed, &plain] {
+            write_file(file, "level");
+        }
+
+        let refs = test_refset();
+
+        // Typed through the script of its target
+        refs.methods.write().await.insert(
+            level_method(),
+            vec![
+                Reference {
+                    file: resolved.clone(),
+                    ..call_ref(Some("abc"))
+                },
+                Reference {
+                    file: plain.clone(),
+                    ..call_ref(None)
+                },
+            ],
+        );
+        refs.errors.write().await.push(FileError {
+            file: failed.clone(),
+            message: "Invalid YAML".to_owned(),
+        });
+
+        let cache_file = save_cache(&dir, &[&resolved, &failed, &plain], &refs).await;
+        let cache = CrawlCache::load(&cache_file, &dir).await;
+        let restored = test_refset();
+
+        for (file, cached) in [(&resolved, false), (&failed, false), (&plain, true)] {
+            let meta = std::fs::metadata(file).unwrap();
+            assert_eq!(cache.restore(file, &meta, &restored).await, cached);
+        }
+
+        _ = std::fs::remove_dir_all(&dir);
+    }
+}
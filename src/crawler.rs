@@ -1,22 +1,29 @@
 use core::fmt::Display;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fs::Metadata;
+use std::hash::Hash;
 use std::path::{Path, PathBuf};
-use std::process::exit;
-use std::sync::{Arc, LazyLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
-use regex::Regex;
-use saphyr::Yaml;
-use tokio::fs::DirEntry;
+use saphyr::MarkedYaml;
+use serde::{Deserialize, Serialize};
 use tokio::io::{self, AsyncReadExt};
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
 use tokio::task::JoinSet;
 use tokio::time::Instant;
 
-use crate::api::status::StatusResponse;
-use crate::yamlparser::search_yaml_doc;
-
-static UNITY_STRIPPED_REGEX: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"(--- .* .*) stripped").unwrap());
+use crate::api::status::{CrawlProgress, StatusResponse};
+use crate::cache::CrawlCache;
+use crate::csharp::{is_assembly_file, is_script_file, parse_script_class, AssemblyDefinitions};
+use crate::filter::PathFilter;
+use crate::hierarchy::{ambiguous_instance_names, find_prefab_root, FileHierarchy, PrefabRoot};
+use crate::metafile::{
+    asset_path, is_meta_file, parse_meta_folder, parse_meta_guid, read_asset_guid, AssetTable,
+};
+use crate::packages::Package;
+use crate::unityyaml::{load_unity_yaml, UnityDocument};
+use crate::yamlparser::{resolve_source_call, search_yaml_doc, source_call_yaml};
 
 #[derive(Debug, Clone)]
 pub struct Crawler {
@@ -24,33 +31,186 @@ pub struct Crawler {
     pub status: Arc<RwLock<StatusResponse>>,
     pub method_refs: Arc<RwLock<HashMap<MethodDefinition, Vec<Reference>>>>,
     pub object_refs: Arc<RwLock<HashMap<ObjectDefinition, Vec<Reference>>>>,
+    pub component_refs: Arc<RwLock<HashMap<ObjectDefinition, Vec<Reference>>>>,
+    pub instance_refs: Arc<RwLock<HashMap<ObjectDefinition, Vec<Reference>>>>,
+    pub assets: Arc<RwLock<AssetTable>>,
+    pub scripts: Arc<RwLock<HashMap<PathBuf, String>>>,
+    pub errors: Arc<RwLock<Vec<FileError>>>,
+    pub progress: Arc<ProgressCounters>,
+    pub status_events: broadcast::Sender<StatusResponse>,
+    pub reference_events: broadcast::Sender<ReferenceChange>,
+    extensions: Arc<Vec<String>>,
+    pub filter: Arc<PathFilter>,
+    pub packages: Arc<Vec<Package>>,
+    assemblies: Arc<AssemblyDefinitions>,
+
+    /// The names of the packages of the project that are not indexed, because
+    /// their files could not be found or packages are not indexed at all
+    pub unindexed_packages: Arc<Vec<String>>,
+
+    cache_file: Option<PathBuf>,
+
+    /// Files that changed while the index was being built, which are
+    /// re-indexed once it is done
+    deferred: Arc<Mutex<HashSet<PathBuf>>>,
 }
 
 #[derive(Debug, Clone)]
 pub struct ArcRefSet {
     pub methods: Arc<RwLock<HashMap<MethodDefinition, Vec<Reference>>>>,
     pub objects: Arc<RwLock<HashMap<ObjectDefinition, Vec<Reference>>>>,
+    pub components: Arc<RwLock<HashMap<ObjectDefinition, Vec<Reference>>>>,
+    pub instances: Arc<RwLock<HashMap<ObjectDefinition, Vec<Reference>>>>,
+    pub assets: Arc<RwLock<AssetTable>>,
+    pub scripts: Arc<RwLock<HashMap<PathBuf, String>>>,
+    pub errors: Arc<RwLock<Vec<FileError>>>,
+    pub progress: Arc<ProgressCounters>,
+    pub extensions: Arc<Vec<String>>,
+    pub filter: Arc<PathFilter>,
+    pub packages: Arc<Vec<Package>>,
+    pub assemblies: Arc<AssemblyDefinitions>,
+
+    /// Only set during the initial crawl, if caching is enabled
+    pub cache: Option<Arc<CrawlCache>>,
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+impl ArcRefSet {
+    /// Looks up the class and assembly of the script with the given GUID
+    pub async fn script_type(&self, guid: &str) -> Option<(String, String)> {
+        let script = self.assets.read().await.path(guid)?.to_path_buf();
+        let class = self.scripts.read().await.get(&script)?.clone();
+
+        let assembly = self.assemblies.script_assembly(&script, &self.assets).await;
+
+        Some((class, assembly))
+    }
+
+    /// Looks up the class and assembly of the call a modified call of a prefab
+    /// instance overrides
+    pub async fn source_call_type(&self, source: &SourceCall) -> Option<(String, String)> {
+        let docs = self.read_asset(&source.prefab).await?;
+        resolve_source_call(source, &docs, self).await
+    }
+
+    /// Looks up the call a modified call of a prefab instance overrides
+    pub async fn source_call(&self, source: &SourceCall) -> Option<MarkedYaml> {
+        let docs = self.read_asset(&source.prefab).await?;
+        source_call_yaml(source, &docs)
+    }
+
+    /// Finds the root GameObject of the prefab with the given GUID
+    pub async fn prefab_root(&self, guid: &str) -> Option<PrefabRoot> {
+        let docs = self.read_asset(guid).await?;
+        find_prefab_root(&docs)
+    }
+
+    async fn read_asset(&self, guid: &str) -> Option<Vec<UnityDocument>> {
+        let asset = self.assets.read().await.path(guid)?.to_path_buf();
+
+        match read_file_to_yaml(&asset).await {
+            Ok(docs) => Some(docs),
+            Err(e) => {
+                log::debug!("Could not read {}: {}", asset.to_string_lossy(), e);
+                None
+            }
+        }
+    }
+}
+
+/// The methods and objects whose references were changed by re-indexing a file
+#[derive(Debug, Clone)]
+pub struct ReferenceChange {
+    pub file: PathBuf,
+    pub methods: HashSet<MethodDefinition>,
+    pub objects: HashSet<ObjectDefinition>,
+}
+
+impl ReferenceChange {
+    fn is_empty(&self) -> bool {
+        self.methods.is_empty() && self.objects.is_empty()
+    }
+}
+
+const STATUS_EVENT_CAPACITY: usize = 16;
+const REFERENCE_EVENT_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MethodDefinition {
     pub method_name: String,
     pub method_assembly: String,
     pub method_typename: String,
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+impl MethodDefinition {
+    /// Animation events only know the name of the method they call, so they are
+    /// stored without an assembly or type
+    pub fn untyped(method_name: String) -> Self {
+        Self {
+            method_name,
+            method_assembly: String::new(),
+            method_typename: String::new(),
+        }
+    }
+
+    pub fn is_untyped(&self) -> bool {
+        self.method_assembly.is_empty() && self.method_typename.is_empty()
+    }
+
+    /// UnityEvent calls whose target type could not be determined
+    pub fn unknown_target(method_name: String) -> Self {
+        Self {
+            method_name,
+            method_assembly: String::new(),
+            method_typename: UNKNOWN_TARGET.to_owned(),
+        }
+    }
+
+    pub fn is_unknown_target(&self) -> bool {
+        self.method_assembly.is_empty() && self.method_typename == UNKNOWN_TARGET
+    }
+}
+
+pub const UNKNOWN_TARGET: &str = "unknown target";
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ObjectDefinition {
     pub guid: String,
 }
 
 impl Crawler {
-    pub async fn new(dir: impl AsRef<Path>) -> Self {
+    pub async fn new(
+        dir: impl AsRef<Path>,
+        extensions: &[String],
+        filter: PathFilter,
+        packages: Vec<Package>,
+        unindexed_packages: Vec<String>,
+        cache_file: Option<PathBuf>,
+    ) -> Self {
+        let extensions = extensions
+            .iter()
+            .map(|ext| ext.trim_start_matches('.').to_owned())
+            .collect();
+
         Self {
             dir: dir.as_ref().to_path_buf(),
             status: Arc::new(RwLock::const_new(StatusResponse::Inactive)),
             method_refs: Arc::new(RwLock::const_new(HashMap::default())),
             object_refs: Arc::new(RwLock::const_new(HashMap::default())),
+            component_refs: Arc::new(RwLock::const_new(HashMap::default())),
+            instance_refs: Arc::new(RwLock::const_new(HashMap::default())),
+            assets: Arc::new(RwLock::const_new(AssetTable::default())),
+            scripts: Arc::new(RwLock::const_new(HashMap::default())),
+            errors: Arc::new(RwLock::const_new(Vec::new())),
+            progress: Arc::new(ProgressCounters::default()),
+            status_events: broadcast::channel(STATUS_EVENT_CAPACITY).0,
+            reference_events: broadcast::channel(REFERENCE_EVENT_CAPACITY).0,
+            extensions: Arc::new(extensions),
+            filter: Arc::new(filter),
+            packages: Arc::new(packages),
+            assemblies: Arc::default(),
+            unindexed_packages: Arc::new(unindexed_packages),
+            cache_file,
+            deferred: Arc::default(),
         }
     }
 
@@ -65,130 +225,999 @@ impl Crawler {
             return;
         }
 
-        *status = StatusResponse::Initializing;
+        *status = StatusResponse::Initializing {
+            progress: CrawlProgress::default(),
+        };
+        self.progress.reset();
+        _ = self.status_events.send(status.clone());
 
         std::mem::drop(status);
 
-        let status_arc = self.status.clone();
-
-        let refset = self.make_refset();
+        let crawler = self.clone();
+        let mut refset = self.make_refset();
 
         let dir = self.dir.clone();
+        let cache_file = self.cache_file.clone();
 
         tokio::spawn(async move {
             log::debug!("Starting crawler");
             let start_time = Instant::now();
 
-            match crawl_dir(&dir, refset).await {
+            if let Some(cache_file) = &cache_file {
+                refset.cache = Some(Arc::new(CrawlCache::load(cache_file, &dir).await));
+            }
+
+            // Everything is discovered before anything is processed, so the
+            // progress covers the full amount of work from the start
+            let crawled = match discover_dir(&dir, &refset).await {
+                Ok(mut files) => {
+                    files.extend(discover_packages(&refset).await);
+                    process_files(files, &refset).await;
+                    resolve_unknown_targets(&refset).await;
+                    Ok(())
+                }
+                Err(e) => Err(e),
+            };
+
+            let new_status = match crawled {
                 Ok(()) => {
-                    *status_arc.write().await = StatusResponse::Ready;
                     log::info!(
                         "Crawler done after {}s",
                         Instant::now().duration_since(start_time).as_secs_f32()
                     );
+
+                    if let (Some(cache), Some(cache_file)) = (&refset.cache, &cache_file) {
+                        if let Err(e) = cache.save(cache_file, &dir, &refset).await {
+                            log::warn!("Could not write index cache: {}", e);
+                        }
+                    }
+
+                    StatusResponse::Ready
                 }
                 Err(e) => {
                     log::error!("Error starting crawler: {}", e);
-                    exit(1);
+                    StatusResponse::Error {
+                        message: e.error.to_string(),
+                        path: Some(e.path.to_string_lossy().to_string()),
+                    }
+                }
+            };
+
+            let mut status = crawler.status.write().await;
+            *status = new_status.clone();
+            _ = crawler.status_events.send(new_status);
+
+            let deferred = std::mem::take(&mut *crawler.deferred.lock().unwrap());
+            let ready = matches!(*status, StatusResponse::Ready);
+
+            std::mem::drop(status);
+
+            // A failed crawl is retried as a whole, which picks up the changes
+            if ready {
+                for path in deferred {
+                    if is_handled_file(&path, &crawler.extensions) {
+                        crawler.reindex_file(&path).await;
+                    } else {
+                        crawler.remove_folder(&path).await;
+                    }
                 }
             }
         });
     }
 
+    /// Throws away the entire index and crawls the folder again. Does nothing
+    /// while a crawl is already in progress.
+    pub async fn restart(&self) {
+        let mut status = self.status.write().await;
+
+        if matches!(*status, StatusResponse::Initializing { .. }) {
+            return;
+        }
+
+        log::info!("Restarting crawler");
+
+        self.method_refs.write().await.clear();
+        self.object_refs.write().await.clear();
+        self.component_refs.write().await.clear();
+        self.instance_refs.write().await.clear();
+        *self.assets.write().await = AssetTable::default();
+        self.scripts.write().await.clear();
+        self.errors.write().await.clear();
+        self.assemblies.clear();
+
+        *status = StatusResponse::Inactive;
+        _ = self.status_events.send(status.clone());
+
+        std::mem::drop(status);
+
+        self.start().await;
+    }
+
+    /// The root of the Unity project, which is the parent of the crawled folder
+    /// if that is the `Assets` folder
+    pub fn project_root(&self) -> &Path {
+        match self.dir.parent() {
+            Some(parent) if self.dir.file_name().is_some_and(|name| name == "Assets") => parent,
+            _ => &self.dir,
+        }
+    }
+
+    /// Whether the references in a file are indexed, as far as its path goes.
+    /// Files serialized in Unity's binary format are skipped when crawling.
+    pub fn is_indexed_file(&self, file: &Path) -> bool {
+        has_indexed_extension(file, &self.extensions) && !self.filter.is_excluded(file, false)
+    }
+
+    /// Resolves a path given by a client, which can be absolute or relative to
+    /// either the crawled folder or the Unity project root
+    /// (`Assets/Foo/Bar.prefab`). Paths within packages can be given the way
+    /// Unity shows them (`Packages/com.company.tools/Foo.prefab`).
+    pub fn resolve_path(&self, path: &Path) -> PathBuf {
+        if path.is_absolute() {
+            return path.to_path_buf();
+        }
+
+        if let Ok(in_packages) = path.strip_prefix("Packages") {
+            let package = self
+                .packages
+                .iter()
+                .find_map(|p| Some(p.path.join(in_packages.strip_prefix(&p.name).ok()?)));
+
+            if let Some(package_path) = package {
+                return package_path;
+            }
+        }
+
+        let in_dir = self.dir.join(path);
+
+        if in_dir.exists() {
+            return in_dir;
+        }
+
+        match self.dir.parent() {
+            Some(parent) if parent.join(path).exists() => parent.join(path),
+            _ => in_dir,
+        }
+    }
+
+    /// Looks up the GUID of the asset at the given path, reading its `.meta`
+    /// file directly in case the crawler has not reached it yet
+    pub async fn asset_guid(&self, asset: &Path) -> io::Result<Option<String>> {
+        if let Some(guid) = self.assets.read().await.guid(asset) {
+            return Ok(Some(guid.to_owned()));
+        }
+
+        read_asset_guid(asset).await
+    }
+
+    /// Brings the index up to date with the current on-disk state of a single
+    /// file. The file is parsed on its own first, after which everything
+    /// originating from it in the index is replaced at once, so no request
+    /// ever sees the file half indexed.
+    pub async fn reindex_file(&self, file: &Path) {
+        // Assembly files are not indexed themselves, but decide the assembly
+        // of the scripts next to and below them
+        if is_assembly_file(file) {
+            self.assemblies.forget(file);
+        }
+
+        if !is_handled_file(file, &self.extensions) || self.filter.is_excluded(file, false) {
+            return;
+        }
+
+        // The crawl may or may not have read the file already, so changes
+        // made during it are only handled once it is done
+        {
+            let status = self.status.read().await;
+
+            if matches!(
+                *status,
+                StatusResponse::Inactive | StatusResponse::Initializing { .. }
+            ) {
+                log::debug!(
+                    "Deferring re-index of {} until the crawl is done",
+                    file.to_string_lossy()
+                );
+
+                self.deferred.lock().unwrap().insert(file.to_path_buf());
+                return;
+            }
+        }
+
+        log::debug!("Re-indexing file {}", file.to_string_lossy());
+
+        let indexed = match tokio::fs::metadata(file).await {
+            Ok(meta) if meta.is_file() => {
+                let refs = self.make_file_refset(file);
+                handle_file(file, refs.clone()).await;
+                resolve_unknown_targets(&refs).await;
+                Some(refs)
+            }
+            Ok(_) => None,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                log::debug!("File {} was removed", file.to_string_lossy());
+                None
+            }
+            Err(e) => {
+                log::warn!(
+                    "Error while reading metadata of {}: {}",
+                    file.to_string_lossy(),
+                    e
+                );
+                None
+            }
+        };
+
+        let change = self.replace_file_refs(file, indexed).await;
+
+        // Calls whose script only just became known can now be resolved
+        if is_meta_file(file) || is_script_file(file) {
+            resolve_unknown_targets(&self.make_refset()).await;
+        }
+
+        if !change.is_empty() {
+            _ = self.reference_events.send(change);
+        }
+    }
+
+    /// Drops everything originating from a removed or renamed folder from the
+    /// index. The files in it are not reported one by one, so none of them
+    /// would be re-indexed on their own.
+    pub async fn remove_folder(&self, dir: &Path) {
+        {
+            let status = self.status.read().await;
+
+            if matches!(
+                *status,
+                StatusResponse::Inactive | StatusResponse::Initializing { .. }
+            ) {
+                log::debug!(
+                    "Deferring removal of {} until the crawl is done",
+                    dir.to_string_lossy()
+                );
+
+                self.deferred.lock().unwrap().insert(dir.to_path_buf());
+                return;
+            }
+        }
+
+        // The folder may be back by the time a deferred removal is handled,
+        // in which case the crawl has indexed it as it is now
+        if tokio::fs::metadata(dir).await.is_ok() {
+            return;
+        }
+
+        log::debug!("Folder {} was removed", dir.to_string_lossy());
+
+        let mut change = ReferenceChange {
+            file: dir.to_path_buf(),
+            methods: HashSet::new(),
+            objects: HashSet::new(),
+        };
+
+        self.errors
+            .write()
+            .await
+            .retain(|e| !e.file.starts_with(dir));
+        self.assets.write().await.remove_folder(dir);
+        self.scripts
+            .write()
+            .await
+            .retain(|script, _| !script.starts_with(dir));
+
+        {
+            let mut methods = self.method_refs.write().await;
+            let mut objects = self.object_refs.write().await;
+            let mut components = self.component_refs.write().await;
+            let mut instances = self.instance_refs.write().await;
+
+            change.methods.extend(remove_refs_in(&mut methods, dir));
+            change.objects.extend(remove_refs_in(&mut objects, dir));
+            change.objects.extend(remove_refs_in(&mut components, dir));
+            change.objects.extend(remove_refs_in(&mut instances, dir));
+        }
+
+        if !change.is_empty() {
+            _ = self.reference_events.send(change);
+        }
+    }
+
+    /// Replaces everything originating from the given file in the index with
+    /// what it was indexed into, if anything, returning the methods and
+    /// objects whose references changed
+    async fn replace_file_refs(&self, file: &Path, indexed: Option<ArcRefSet>) -> ReferenceChange {
+        let mut change = ReferenceChange {
+            file: file.to_path_buf(),
+            methods: HashSet::new(),
+            objects: HashSet::new(),
+        };
+
+        let new_errors = match &indexed {
+            Some(indexed) => std::mem::take(&mut *indexed.errors.write().await),
+            None => Vec::new(),
+        };
+
+        {
+            let mut errors = self.errors.write().await;
+            errors.retain(|e| e.file != file);
+            errors.extend(new_errors);
+        }
+
+        if is_meta_file(file) {
+            let mut assets = self.assets.write().await;
+            assets.remove_path(&asset_path(file));
+
+            if let Some(indexed) = &indexed {
+                let indexed_assets = indexed.assets.read().await;
+
+                for (guid, asset) in indexed_assets.iter() {
+                    let folder = indexed_assets.is_folder(guid);
+                    assets.insert(guid.to_owned(), asset.to_path_buf(), folder);
+                }
+            }
+
+            return change;
+        }
+
+        if is_script_file(file) {
+            let mut scripts = self.scripts.write().await;
+            scripts.remove(file);
+
+            if let Some(indexed) = &indexed {
+                scripts.extend(indexed.scripts.write().await.drain());
+            }
+
+            return change;
+        }
+
+        let (new_methods, new_objects, new_components, new_instances) = match &indexed {
+            Some(indexed) => (
+                std::mem::take(&mut *indexed.methods.write().await),
+                std::mem::take(&mut *indexed.objects.write().await),
+                std::mem::take(&mut *indexed.components.write().await),
+                std::mem::take(&mut *indexed.instances.write().await),
+            ),
+            None => Default::default(),
+        };
+
+        // All locks are held together, so the file is replaced in every part
+        // of the index at once
+        let mut methods = self.method_refs.write().await;
+        let mut objects = self.object_refs.write().await;
+        let mut components = self.component_refs.write().await;
+        let mut instances = self.instance_refs.write().await;
+
+        change
+            .methods
+            .extend(replace_refs_in(&mut methods, file, new_methods));
+        change
+            .objects
+            .extend(replace_refs_in(&mut objects, file, new_objects));
+        change
+            .objects
+            .extend(replace_refs_in(&mut components, file, new_components));
+        change
+            .objects
+            .extend(replace_refs_in(&mut instances, file, new_instances));
+
+        change
+    }
+
+    /// A refset to index a single file into, separate from the index itself.
+    /// Scripts and assets are still looked up in the index, unless the file
+    /// adds to those tables itself.
+    fn make_file_refset(&self, file: &Path) -> ArcRefSet {
+        let own_tables = is_meta_file(file) || is_script_file(file);
+
+        ArcRefSet {
+            methods: Arc::default(),
+            objects: Arc::default(),
+            components: Arc::default(),
+            instances: Arc::default(),
+            assets: if own_tables {
+                Arc::default()
+            } else {
+                self.assets.clone()
+            },
+            scripts: if own_tables {
+                Arc::default()
+            } else {
+                self.scripts.clone()
+            },
+            errors: Arc::default(),
+            progress: self.progress.clone(),
+            extensions: self.extensions.clone(),
+            filter: self.filter.clone(),
+            packages: self.packages.clone(),
+            assemblies: self.assemblies.clone(),
+            cache: None,
+        }
+    }
+
     fn make_refset(&self) -> ArcRefSet {
         ArcRefSet {
             methods: self.method_refs.clone(),
             objects: self.object_refs.clone(),
+            components: self.component_refs.clone(),
+            instances: self.instance_refs.clone(),
+            assets: self.assets.clone(),
+            scripts: self.scripts.clone(),
+            errors: self.errors.clone(),
+            progress: self.progress.clone(),
+            extensions: self.extensions.clone(),
+            filter: self.filter.clone(),
+            packages: self.packages.clone(),
+            assemblies: self.assemblies.clone(),
+            cache: None,
         }
     }
 }
 
-const EXTENSIONS: &[&str] = &["unity", "prefab"];
+/// Drops the references originating from the given file and adds the new
+/// ones, returning the keys whose references changed
+/// Removes all references from files inside the given folder, returning the
+/// keys whose references changed
+fn remove_refs_in<K: Clone + Eq + Hash>(
+    refs: &mut HashMap<K, Vec<Reference>>,
+    dir: &Path,
+) -> HashSet<K> {
+    let mut changed = HashSet::new();
 
-async fn crawl_dir(dir: &Path, refs: ArcRefSet) -> io::Result<()> {
-    log::debug!("Crawling directory {}", dir.to_string_lossy());
+    refs.retain(|key, dir_refs| {
+        let old_len = dir_refs.len();
+        dir_refs.retain(|r| !r.file.starts_with(dir));
+
+        if dir_refs.len() != old_len {
+            changed.insert(key.clone());
+        }
+
+        !dir_refs.is_empty()
+    });
+
+    changed
+}
+
+fn replace_refs_in<K: Clone + Eq + Hash>(
+    refs: &mut HashMap<K, Vec<Reference>>,
+    file: &Path,
+    new_refs: HashMap<K, Vec<Reference>>,
+) -> HashSet<K> {
+    let mut changed = HashSet::new();
+
+    refs.retain(|key, file_refs| {
+        let old_len = file_refs.len();
+        file_refs.retain(|r| r.file != file);
+
+        if file_refs.len() != old_len {
+            changed.insert(key.clone());
+        }
 
-    let mut files = tokio::fs::read_dir(dir).await?;
+        !file_refs.is_empty()
+    });
 
-    let mut tasks = JoinSet::new();
+    for (key, key_refs) in new_refs {
+        changed.insert(key.clone());
+        refs.entry(key).or_default().extend(key_refs);
+    }
 
-    while let Some(item) = files.next_entry().await? {
-        crawl_dir_entry(item, &mut tasks, refs.clone());
+    changed
+}
+
+/// Turns the object index around, into the GUIDs referenced by each file
+pub fn guids_by_file(
+    object_refs: &HashMap<ObjectDefinition, Vec<Reference>>,
+) -> HashMap<PathBuf, HashSet<String>> {
+    let mut by_file: HashMap<PathBuf, HashSet<String>> = HashMap::new();
+
+    for (object, file_refs) in object_refs {
+        for r in file_refs {
+            by_file
+                .entry(r.file.clone())
+                .or_default()
+                .insert(object.guid.clone());
+        }
     }
 
-    tasks.join_all().await;
+    by_file
+}
+
+/// The text-serialized Unity file types that are indexed when no others are
+/// configured
+pub const DEFAULT_EXTENSIONS: &str =
+    "unity,prefab,asset,controller,anim,overrideController,mat,playable";
 
-    Ok(())
+fn has_indexed_extension(file: &Path, extensions: &[String]) -> bool {
+    file.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.iter().any(|indexed| indexed == ext))
 }
 
-fn crawl_dir_entry(item: DirEntry, join_set: &mut JoinSet<()>, refs: ArcRefSet) {
-    join_set.spawn(async move {
-        let item_type = item.file_type().await.unwrap();
+fn is_handled_file(file: &Path, extensions: &[String]) -> bool {
+    has_indexed_extension(file, extensions) || is_meta_file(file) || is_script_file(file)
+}
+
+/// Counters for the progress of the initial crawl, which are updated
+/// concurrently by all crawling tasks
+#[derive(Debug, Default)]
+pub struct ProgressCounters {
+    discovered_files: AtomicU64,
+    discovered_bytes: AtomicU64,
+    processed_files: AtomicU64,
+    processed_bytes: AtomicU64,
+    started: Mutex<Option<Instant>>,
+}
+
+impl ProgressCounters {
+    fn reset(&self) {
+        self.discovered_files.store(0, Ordering::Relaxed);
+        self.discovered_bytes.store(0, Ordering::Relaxed);
+        self.processed_files.store(0, Ordering::Relaxed);
+        self.processed_bytes.store(0, Ordering::Relaxed);
+        *self.started.lock().unwrap() = Some(Instant::now());
+    }
+
+    fn discover(&self, bytes: u64) {
+        self.discovered_files.fetch_add(1, Ordering::Relaxed);
+        self.discovered_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn process(&self, bytes: u64) {
+        self.processed_files.fetch_add(1, Ordering::Relaxed);
+        self.processed_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> CrawlProgress {
+        let discovered_bytes = self.discovered_bytes.load(Ordering::Relaxed);
+        let processed_bytes = self.processed_bytes.load(Ordering::Relaxed);
+
+        let elapsed = self
+            .started
+            .lock()
+            .unwrap()
+            .map(|started| started.elapsed().as_secs_f64())
+            .unwrap_or_default();
+
+        // Parsing time scales with file size, so the estimate is based on bytes.
+        // All files are discovered before any of them is processed, so there
+        // is no estimate until processing starts.
+        let eta = (processed_bytes > 0).then(|| {
+            let remaining = discovered_bytes.saturating_sub(processed_bytes);
+            elapsed * remaining as f64 / processed_bytes as f64
+        });
+
+        CrawlProgress {
+            discovered_files: self.discovered_files.load(Ordering::Relaxed),
+            processed_files: self.processed_files.load(Ordering::Relaxed),
+            discovered_bytes,
+            processed_bytes,
+            elapsed,
+            eta,
+        }
+    }
+}
+
+/// An error that prevented a directory from being crawled
+#[derive(Debug)]
+struct CrawlError {
+    path: PathBuf,
+    error: io::Error,
+}
+
+impl Display for CrawlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path.to_string_lossy(), self.error)
+    }
+}
+
+/// An error encountered while indexing a single file or directory, which did
+/// not stop the rest of the crawl
+#[derive(Debug, Clone)]
+pub struct FileError {
+    pub file: PathBuf,
+    pub message: String,
+}
+
+async fn record_error(refs: &ArcRefSet, file: &Path, message: String) {
+    log::warn!("{}: {}", file.to_string_lossy(), message);
+
+    refs.errors.write().await.push(FileError {
+        file: file.to_path_buf(),
+        message,
+    });
+}
+
+/// A file to index, found while discovering the files of a folder
+struct FoundFile {
+    path: PathBuf,
+    meta: Option<Metadata>,
+}
+
+/// Finds every file to index below a folder, counting each of them towards
+/// the progress as it is found
+async fn discover_dir(dir: &Path, refs: &ArcRefSet) -> Result<Vec<FoundFile>, CrawlError> {
+    log::debug!("Crawling directory {}", dir.to_string_lossy());
+
+    let to_crawl_error = |error| CrawlError {
+        path: dir.to_path_buf(),
+        error,
+    };
+
+    let mut entries = tokio::fs::read_dir(dir).await.map_err(to_crawl_error)?;
+
+    let mut subdirs = JoinSet::new();
+    let mut found = Vec::new();
+
+    while let Some(item) = entries.next_entry().await.map_err(to_crawl_error)? {
+        let path = item.path();
+
+        let item_type = match item.file_type().await {
+            Ok(t) => t,
+            Err(e) => {
+                record_error(refs, &path, format!("Could not read file type: {}", e)).await;
+                continue;
+            }
+        };
+
+        if refs.filter.is_excluded(&path, item_type.is_dir()) {
+            log::debug!("Skipping excluded path {}", path.to_string_lossy());
+            continue;
+        }
 
         if item_type.is_dir() {
-            if let Err(e) = crawl_dir(&item.path(), refs).await {
-                log::warn!(
-                    "Error while trying to crawl subdirectory {}: {}",
-                    item.path().to_string_lossy(),
-                    e
-                );
+            // Packages inside the crawled folder are crawled as their own root
+            if refs.packages.iter().any(|p| p.path == path) {
+                continue;
             }
+
+            discover_subdir(path, &mut subdirs, refs.clone());
         } else if item_type.is_file() {
-            handle_file(&item.path(), refs).await;
+            if !is_handled_file(&path, &refs.extensions) {
+                continue;
+            }
+
+            let meta = item.metadata().await.ok();
+            refs.progress
+                .discover(meta.as_ref().map(|m| m.len()).unwrap_or_default());
+
+            found.push(FoundFile { path, meta });
         } else if item_type.is_symlink() {
-            log::warn!("Skipping symlink at {}", item.path().to_string_lossy());
+            log::warn!("Skipping symlink at {}", path.to_string_lossy());
         } else {
             log::warn!(
                 "Unknown filetype, cannot handle it (or any subdirectories): {:#?}",
                 item_type
             );
         }
+    }
+
+    while let Some(result) = subdirs.join_next().await {
+        match result {
+            Ok(files) => found.extend(files),
+            Err(e) => {
+                record_error(
+                    refs,
+                    dir,
+                    format!("Error while trying to crawl subdirectory: {}", e),
+                )
+                .await;
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+/// Subdirectories are discovered in their own task. Unlike the crawled folder
+/// itself, a subdirectory that cannot be read does not fail the entire crawl.
+fn discover_subdir(dir: PathBuf, join_set: &mut JoinSet<Vec<FoundFile>>, refs: ArcRefSet) {
+    join_set.spawn(async move {
+        match discover_dir(&dir, &refs).await {
+            Ok(files) => files,
+            Err(e) => {
+                record_error(
+                    &refs,
+                    &e.path,
+                    format!("Error while trying to crawl subdirectory: {}", e.error),
+                )
+                .await;
+
+                Vec::new()
+            }
+        }
     });
 }
 
-async fn handle_file(file: &Path, refs: ArcRefSet) {
-    if let Some(extension) = file.extension().and_then(|ext| ext.to_str()) {
-        if EXTENSIONS.contains(&extension) {
-            log::debug!("Found possible file: {}", file.to_string_lossy());
-            let parsed = match read_file_to_yaml(file).await {
-                Ok(p) => p,
-                Err(e) => {
-                    log::warn!(
-                        "Error reading or parsing file {}: {}",
-                        file.to_string_lossy(),
-                        e
-                    );
-                    return;
+/// Discovers the files of all packages. Unlike the crawled folder itself, a
+/// package that cannot be read does not fail the entire crawl.
+async fn discover_packages(refs: &ArcRefSet) -> Vec<FoundFile> {
+    let mut found = Vec::new();
+
+    for package in refs.packages.iter() {
+        log::debug!("Crawling package {}", package.name);
+
+        match discover_dir(&package.path, refs).await {
+            Ok(files) => found.extend(files),
+            Err(e) => {
+                record_error(
+                    refs,
+                    &e.path,
+                    format!(
+                        "Error while trying to crawl package {}: {}",
+                        package.name, e.error
+                    ),
+                )
+                .await;
+            }
+        }
+    }
+
+    found
+}
+
+/// Indexes the discovered files, restoring unchanged files from the cache.
+/// Meta files and scripts are indexed first, so every asset and script is
+/// known by the time other files look them up.
+async fn process_files(files: Vec<FoundFile>, refs: &ArcRefSet) {
+    let (tables, others): (Vec<_>, Vec<_>) = files
+        .into_iter()
+        .partition(|file| is_meta_file(&file.path) || is_script_file(&file.path));
+
+    for batch in [tables, others] {
+        let mut tasks = JoinSet::new();
+
+        for file in batch {
+            let refs = refs.clone();
+
+            tasks.spawn(async move {
+                let size = file.meta.as_ref().map(|m| m.len()).unwrap_or_default();
+
+                let restored = match (&refs.cache, &file.meta) {
+                    (Some(cache), Some(meta)) => cache.restore(&file.path, meta, &refs).await,
+                    _ => false,
+                };
+
+                if !restored {
+                    handle_file(&file.path, refs.clone()).await;
                 }
+
+                refs.progress.process(size);
+            });
+        }
+
+        tasks.join_all().await;
+    }
+}
+
+/// Calls without a type or assembly name are resolved through the script of
+/// their target, which may not have been indexed yet when the call itself was
+/// found
+async fn resolve_unknown_targets(refs: &ArcRefSet) {
+    // Looking up a script type reads other tables and files, so the types are
+    // looked up before locking the methods instead of while holding the lock
+    let mut scripts = HashSet::new();
+    let mut sources = HashSet::new();
+
+    for (_, method_refs) in refs
+        .methods
+        .read()
+        .await
+        .iter()
+        .filter(|(method, _)| is_unresolved(method))
+    {
+        for call in method_refs.iter().filter_map(unity_event_call) {
+            scripts.extend(call.script.clone());
+            sources.extend(call.source.clone());
+        }
+    }
+
+    if scripts.is_empty() && sources.is_empty() {
+        return;
+    }
+
+    let mut script_types = HashMap::new();
+
+    for script in scripts {
+        if let Some(script_type) = refs.script_type(&script).await {
+            script_types.insert(script, script_type);
+        }
+    }
+
+    let mut source_types = HashMap::new();
+
+    for source in sources {
+        if let Some(source_type) = refs.source_call_type(&source).await {
+            source_types.insert(source, source_type);
+        }
+    }
+
+    let mut methods = refs.methods.write().await;
+
+    let unknown: Vec<MethodDefinition> = methods
+        .keys()
+        .filter(|method| is_unresolved(method))
+        .cloned()
+        .collect();
+
+    for method in unknown {
+        let Some(method_refs) = methods.remove(&method) else {
+            continue;
+        };
+
+        for r in method_refs {
+            // The script of the target itself takes precedence over the call
+            // that is overridden
+            let found_type = unity_event_call(&r).and_then(|call| {
+                call.script
+                    .as_ref()
+                    .and_then(|script| script_types.get(script))
+                    .or_else(|| {
+                        call.source
+                            .as_ref()
+                            .and_then(|source| source_types.get(source))
+                    })
+            });
+
+            let key = match found_type {
+                Some((class, assembly)) => MethodDefinition {
+                    method_name: method.method_name.clone(),
+                    method_assembly: assembly.clone(),
+                    method_typename: if method.is_unknown_target() {
+                        class.clone()
+                    } else {
+                        method.method_typename.clone()
+                    },
+                },
+                None => method.clone(),
             };
 
-            log::debug!("Parsed {} succesfully", file.to_string_lossy());
+            methods.entry(key).or_default().push(r);
+        }
+    }
+}
+
+/// Calls missing their type or assembly. Animation events never have either.
+fn is_unresolved(method: &MethodDefinition) -> bool {
+    method.method_assembly.is_empty() && !method.is_untyped()
+}
+
+fn unity_event_call(r: &Reference) -> Option<&UnityEventCall> {
+    match &r.kind {
+        ReferenceKind::UnityEvent(call) => Some(call),
+        _ => None,
+    }
+}
 
-            let mut document_tasks = JoinSet::new();
+async fn handle_file(file: &Path, refs: ArcRefSet) {
+    if is_meta_file(file) {
+        handle_meta_file(file, refs).await;
+        return;
+    }
 
-            let file_arc = Arc::new(file.to_path_buf());
+    if is_script_file(file) {
+        handle_script_file(file, refs).await;
+        return;
+    }
 
-            for doc in parsed {
-                let file_cloned = file_arc.clone();
-                let refs_cloned = refs.clone();
-                document_tasks.spawn(async move {
-                    log::trace!(
-                        "Searching document in file {}",
-                        file_cloned.to_string_lossy()
-                    );
-                    search_yaml_doc(&doc, &refs_cloned, &file_cloned).await;
-                });
-            }
+    if !has_indexed_extension(file, &refs.extensions) {
+        return;
+    }
+
+    log::debug!("Found possible file: {}", file.to_string_lossy());
+    let parsed = match read_file_to_yaml(file).await {
+        Ok(p) => p,
+        Err(ReadErr::NotText) => {
+            log::debug!(
+                "Skipping {}, it is not serialized as text",
+                file.to_string_lossy()
+            );
+            return;
+        }
+        Err(e) => {
+            record_error(&refs, file, format!("Error reading or parsing file: {}", e)).await;
+            return;
+        }
+    };
+
+    log::debug!("Parsed {} succesfully", file.to_string_lossy());
+
+    // Prefab instances that rename several objects need the root of their
+    // prefab to tell which name is their own
+    let ambiguous = ambiguous_instance_names(&parsed);
+    let mut prefab_roots = HashMap::new();
+
+    for guid in &ambiguous {
+        if let Some(root) = refs.prefab_root(guid).await {
+            prefab_roots.insert(guid.clone(), root);
+        }
+    }
 
-            document_tasks.join_all().await;
+    // The object paths of the file then go stale when those prefabs change
+    if !ambiguous.is_empty() {
+        if let Some(cache) = &refs.cache {
+            cache.leave_out(file);
         }
     }
+
+    let hierarchy = Arc::new(FileHierarchy::from_documents(&parsed, &prefab_roots));
+
+    let mut document_tasks = JoinSet::new();
+
+    let file_arc = Arc::new(file.to_path_buf());
+
+    for doc in parsed {
+        let file_cloned = file_arc.clone();
+        let refs_cloned = refs.clone();
+        let hierarchy_cloned = hierarchy.clone();
+        document_tasks.spawn(async move {
+            log::trace!(
+                "Searching document in file {}",
+                file_cloned.to_string_lossy()
+            );
+            search_yaml_doc(&doc, &refs_cloned, &file_cloned, &hierarchy_cloned).await;
+        });
+    }
+
+    while let Some(result) = document_tasks.join_next().await {
+        if let Err(e) = result {
+            record_error(
+                &refs,
+                file,
+                format!("Error while searching document: {}", e),
+            )
+            .await;
+        }
+    }
+}
+
+async fn handle_meta_file(file: &Path, refs: ArcRefSet) {
+    let content = match tokio::fs::read_to_string(file).await {
+        Ok(c) => c,
+        Err(e) => {
+            record_error(&refs, file, format!("Error reading meta file: {}", e)).await;
+            return;
+        }
+    };
+
+    match parse_meta_guid(&content) {
+        Some(guid) => {
+            let folder = parse_meta_folder(&content);
+            refs.assets
+                .write()
+                .await
+                .insert(guid, asset_path(file), folder);
+        }
+        None => log::warn!("No GUID found in meta file {}", file.to_string_lossy()),
+    }
+}
+
+async fn handle_script_file(file: &Path, refs: ArcRefSet) {
+    let content = match tokio::fs::read_to_string(file).await {
+        Ok(c) => c,
+        Err(e) => {
+            record_error(&refs, file, format!("Error reading script: {}", e)).await;
+            return;
+        }
+    };
+
+    let file_stem = file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+
+    match parse_script_class(&content, &file_stem) {
+        Some(class) => {
+            log::trace!("Script {} defines {}", file.to_string_lossy(), class);
+            refs.scripts.write().await.insert(file.to_path_buf(), class);
+        }
+        None => log::debug!("No class found in script {}", file.to_string_lossy()),
+    }
 }
 
 #[derive(Debug)]
 enum ReadErr {
     Io(io::Error),
     Yaml(saphyr::ScanError),
+    NotText,
 }
 
 impl Display for ReadErr {
@@ -196,6 +1225,7 @@ impl Display for ReadErr {
         match self {
             ReadErr::Io(error) => error.fmt(f),
             ReadErr::Yaml(error) => error.fmt(f),
+            ReadErr::NotText => write!(f, "File is not serialized as YAML text"),
         }
     }
 }
@@ -212,22 +1242,26 @@ impl From<saphyr::ScanError> for ReadErr {
     }
 }
 
-async fn read_file_to_yaml(file: &Path) -> Result<Vec<Yaml>, ReadErr> {
+async fn read_file_to_yaml(file: &Path) -> Result<Vec<UnityDocument>, ReadErr> {
     let mut open_file = tokio::fs::File::open(file).await?;
 
-    let mut content = String::new();
+    let mut raw_content = Vec::new();
 
-    open_file.read_to_string(&mut content).await?;
+    open_file.read_to_end(&mut raw_content).await?;
 
-    // Dirty hack to get around Unity's broken YAML implementation before I find
-    // a proper solution
-    let cleaned = UNITY_STRIPPED_REGEX.replace_all(&content, "$1");
+    // Assets can also be serialized in Unity's binary format, depending on the
+    // project settings and the asset type
+    if !raw_content.starts_with(b"%YAML") {
+        return Err(ReadErr::NotText);
+    }
 
-    let mut parser = saphyr_parser::Parser::new_from_str(&cleaned).keep_tags(true);
-    Ok(Yaml::load_from_parser(&mut parser)?)
+    let content = String::from_utf8(raw_content)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    Ok(load_unity_yaml(&content)?)
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Reference {
     /// Which file? (`/MyProject/MyScene.unity`)
     pub file: PathBuf,
@@ -240,4 +1274,356 @@ pub struct Reference {
 
     /// The human readable path to the referencing object within the asset (`GameObject A`-> `GameObject B` -> etc.)
     pub object: Option<Vec<String>>,
+
+    /// The name of the package containing the file, if it is not part of the
+    /// project itself (`com.company.tools`)
+    pub package: Option<String>,
+
+    /// What kind of serialized data the reference comes from
+    pub kind: ReferenceKind,
+}
+
+impl Reference {
+    /// Whether the reference was completed using the contents of other files,
+    /// which can change without the referencing file changing
+    pub fn depends_on_other_files(&self) -> bool {
+        match &self.kind {
+            ReferenceKind::UnityEvent(call) => call.script.is_some() || call.source.is_some(),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ReferenceKind {
+    /// A reference to an object from a serialized field, with the property
+    /// path of that field (`m_Materials.Array.data[0]`) if it is known
+    Object { property: Option<String> },
+
+    /// A listener of a UnityEvent
+    UnityEvent(Box<UnityEventCall>),
+
+    /// An event in an AnimationClip, which is fired at the given time (in
+    /// seconds) into the clip
+    AnimationEvent { clip: String, time: f64 },
+}
+
+/// How a UnityEvent listener is set up in the inspector
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnityEventCall {
+    /// The object the method is called on
+    pub target: Option<ObjectPointer>,
+
+    /// Which argument the method is called with
+    pub mode: Option<ListenerMode>,
+
+    /// The argument set in the inspector, if the mode takes one
+    pub argument: Option<StaticArgument>,
+
+    /// When the listener is called
+    pub call_state: Option<CallState>,
+
+    /// The GUID of the script of the target, used to find the type of calls
+    /// that do not store it
+    pub script: Option<String>,
+
+    /// The call in the source prefab a modified call of a prefab instance
+    /// overrides, used to find the type of calls that only override some of
+    /// their fields
+    pub source: Option<SourceCall>,
+}
+
+/// A persistent call in a prefab, as overridden by instances of the prefab
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceCall {
+    /// The GUID of the source prefab
+    pub prefab: String,
+
+    /// The object in the source prefab the event belongs to
+    pub file_id: i64,
+
+    /// The property path of the call
+    /// (`m_OnClick.m_PersistentCalls.m_Calls.Array.data[0]`)
+    pub property: String,
+}
+
+/// An object reference (`{fileID: x, guid: y}`). Without a GUID, the object is
+/// in the same file as the reference.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObjectPointer {
+    pub file_id: i64,
+    pub guid: Option<String>,
+
+    /// The path to the GameObject within the file, for objects in the same file
+    pub object: Option<Vec<String>>,
+}
+
+/// `PersistentListenerMode` in Unity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListenerMode {
+    /// Called with the arguments of the event itself
+    Dynamic,
+    Void,
+    Object,
+    Int,
+    Float,
+    String,
+    Bool,
+}
+
+impl ListenerMode {
+    pub fn from_serialized(mode: i64) -> Option<Self> {
+        match mode {
+            0 => Some(Self::Dynamic),
+            1 => Some(Self::Void),
+            2 => Some(Self::Object),
+            3 => Some(Self::Int),
+            4 => Some(Self::Float),
+            5 => Some(Self::String),
+            6 => Some(Self::Bool),
+            _ => None,
+        }
+    }
+}
+
+/// An argument set in the inspector, stored in `m_Arguments`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StaticArgument {
+    Object {
+        object: Option<ObjectPointer>,
+        type_name: Option<String>,
+    },
+    Int(i64),
+    Float(f64),
+    String(String),
+    Bool(bool),
+}
+
+/// `UnityEventCallState` in Unity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallState {
+    Off,
+    EditorAndRuntime,
+    RuntimeOnly,
+}
+
+impl CallState {
+    pub fn from_serialized(state: i64) -> Option<Self> {
+        match state {
+            0 => Some(Self::Off),
+            1 => Some(Self::EditorAndRuntime),
+            2 => Some(Self::RuntimeOnly),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub fn test_refset() -> ArcRefSet {
+        let root = Path::new("/project");
+
+        ArcRefSet {
+            methods: Arc::default(),
+            objects: Arc::default(),
+            components: Arc::default(),
+            instances: Arc::default(),
+            assets: Arc::default(),
+            scripts: Arc::default(),
+            errors: Arc::default(),
+            progress: Arc::default(),
+            extensions: Arc::default(),
+            filter: Arc::new(PathFilter::new(root, &[], &[], false).unwrap()),
+            packages: Arc::default(),
+            assemblies: Arc::default(),
+            cache: None,
+        }
+    }
+
+    /// An empty folder of its own for every test, as they run in parallel
+    pub fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "unity-reference-server-{}-{}",
+            name,
+            std::process::id()
+        ));
+
+        _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    pub fn call_ref(script: Option<&str>) -> Reference {
+        Reference {
+            file: PathBuf::from("/project/Assets/Level.unity"),
+            line: Some(10),
+            asset: None,
+            object: None,
+            package: None,
+            kind: ReferenceKind::UnityEvent(Box::new(UnityEventCall {
+                target: None,
+                mode: Some(ListenerMode::Void),
+                argument: None,
+                call_state: Some(CallState::RuntimeOnly),
+                script: script.map(str::to_owned),
+                source: None,
+            })),
+        }
+    }
+
+    #[tokio::test]
+    async fn resolves_calls_through_their_script() {
+        let refs = test_refset();
+        let script = PathBuf::from("/project/Assets/Menu.cs");
+
+        refs.assets
+            .write()
+            .await
+            .insert("abc".to_owned(), script.clone(), false);
+        refs.scripts
+            .write()
+            .await
+            .insert(script, "Game.Menu".to_owned());
+
+        {
+            let mut methods = refs.methods.write().await;
+
+            methods
+                .entry(MethodDefinition::unknown_target("OnPlay".to_owned()))
+                .or_default()
+                .extend([call_ref(Some("abc")), call_ref(Some("missing"))]);
+
+            // Older versions of Unity may store the type without the assembly
+            methods
+                .entry(MethodDefinition {
+                    method_name: "OnQuit".to_owned(),
+                    method_assembly: String::new(),
+                    method_typename: "Game.Quit".to_owned(),
+                })
+                .or_default()
+                .push(call_ref(Some("abc")));
+        }
+
+        resolve_unknown_targets(&refs).await;
+
+        let methods = refs.methods.read().await;
+
+        let resolved = MethodDefinition {
+            method_name: "OnPlay".to_owned(),
+            method_assembly: "Assembly-CSharp".to_owned(),
+            method_typename: "Game.Menu".to_owned(),
+        };
+
+        let with_assembly = MethodDefinition {
+            method_name: "OnQuit".to_owned(),
+            method_assembly: "Assembly-CSharp".to_owned(),
+            method_typename: "Game.Quit".to_owned(),
+        };
+
+        assert_eq!(methods[&resolved].len(), 1);
+        assert_eq!(methods[&with_assembly].len(), 1);
+        assert_eq!(
+            methods[&MethodDefinition::unknown_target("OnPlay".to_owned())].len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn leaves_animation_events_untyped() {
+        let refs = test_refset();
+        let untyped = MethodDefinition::untyped("Footstep".to_owned());
+
+        refs.methods
+            .write()
+            .await
+            .entry(untyped.clone())
+            .or_default()
+            .push(Reference {
+                kind: ReferenceKind::AnimationEvent {
+                    clip: "Walk".to_owned(),
+                    time: 0.5,
+                },
+                ..call_ref(None)
+            });
+
+        resolve_unknown_targets(&refs).await;
+
+        assert_eq!(refs.methods.read().await[&untyped].len(), 1);
+    }
+
+    #[tokio::test]
+    async fn drops_everything_in_removed_folders() {
+        let root = Path::new("/project");
+        let filter = PathFilter::new(root, &[], &[], false).unwrap();
+        let crawler = Crawler::new(root, &[], filter, Vec::new(), Vec::new(), None).await;
+        *crawler.status.write().await = StatusResponse::Ready;
+
+        let mut events = crawler.reference_events.subscribe();
+
+        let removed = PathBuf::from("/project/Assets/Old");
+        let kept = PathBuf::from("/project/Assets/Older/Level.unity");
+        let script = removed.join("Menu.cs");
+        let method = MethodDefinition::unknown_target("OnPlay".to_owned());
+        let object = ObjectDefinition {
+            guid: "abc".to_owned(),
+        };
+
+        {
+            let mut assets = crawler.assets.write().await;
+            assets.insert("abc".to_owned(), script.clone(), false);
+            assets.insert("def".to_owned(), removed.clone(), true);
+            assets.insert("ghi".to_owned(), kept.clone(), false);
+        }
+
+        crawler
+            .scripts
+            .write()
+            .await
+            .insert(script, "Game.Menu".to_owned());
+
+        crawler.method_refs.write().await.insert(
+            method.clone(),
+            vec![
+                Reference {
+                    file: removed.join("Menu.prefab"),
+                    ..call_ref(None)
+                },
+                Reference {
+                    file: kept.clone(),
+                    ..call_ref(None)
+                },
+            ],
+        );
+
+        crawler.object_refs.write().await.insert(
+            object.clone(),
+            vec![Reference {
+                file: removed.join("Nested/Menu.prefab"),
+                ..call_ref(None)
+            }],
+        );
+
+        crawler.remove_folder(&removed).await;
+
+        let methods = crawler.method_refs.read().await;
+        assert_eq!(methods[&method].len(), 1);
+        assert_eq!(methods[&method][0].file, kept);
+        assert!(crawler.object_refs.read().await.is_empty());
+        assert!(crawler.scripts.read().await.is_empty());
+
+        let assets = crawler.assets.read().await;
+        assert_eq!(assets.path("abc"), None);
+        assert_eq!(assets.path("def"), None);
+        assert_eq!(assets.path("ghi"), Some(kept.as_path()));
+
+        let change = events.try_recv().unwrap();
+        assert_eq!(change.file, removed);
+        assert_eq!(change.methods, HashSet::from([method]));
+        assert_eq!(change.objects, HashSet::from([object]));
+        assert!(events.try_recv().is_err());
+    }
 }
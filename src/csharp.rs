@@ -0,0 +1,436 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+use regex::Regex;
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use crate::metafile::AssetTable;
+
+static NAMESPACE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s*namespace\s+([\w.]+)").unwrap());
+
+/// Class declarations, which start their line apart from attributes and
+/// modifiers. This leaves out `class` constraints (`where T : class`).
+static CLASS_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?m)^[ \t]*(?:\[[^\]\n]*\][ \t]*)*(?:(?:public|internal|protected|private|static|sealed|abstract|partial|unsafe|new)\s+)*class\s+(\w+)",
+    )
+    .unwrap()
+});
+
+/// Whether the given file is a C# script
+pub fn is_script_file(file: &Path) -> bool {
+    file.extension().is_some_and(|ext| ext == "cs")
+}
+
+/// Finds the fully qualified name of the class a script defines. Unity requires
+/// MonoBehaviours and ScriptableObjects to be named after their file, so that
+/// class is preferred if the file contains multiple.
+pub fn parse_script_class(content: &str, file_stem: &str) -> Option<String> {
+    let content = strip_comments_and_strings(content);
+    let mut classes = CLASS_REGEX.captures_iter(&content).map(|c| c[1].to_owned());
+
+    let first = classes.next()?;
+    let class = if first == file_stem {
+        first
+    } else {
+        classes.find(|c| c == file_stem).unwrap_or(first)
+    };
+
+    match NAMESPACE_REGEX.captures(&content) {
+        Some(namespace) => Some(format!("{}.{}", &namespace[1], class)),
+        None => Some(class),
+    }
+}
+
+/// Removes comments and the contents of string and character literals, so
+/// declarations are only searched for in actual code. Line breaks are kept.
+fn strip_comments_and_strings(content: &str) -> String {
+    let chars: Vec<char> = content.chars().collect();
+    let mut stripped = String::with_capacity(content.len());
+    let mut i = 0;
+
+    // Skips ahead to the given character, keeping the line breaks on the way
+    let skip_to = |stripped: &mut String, from: usize, end: &dyn Fn(usize) -> bool| {
+        let mut i = from;
+
+        while i < chars.len() && !end(i) {
+            if chars[i] == '\n' {
+                stripped.push('\n');
+            }
+
+            i += 1;
+        }
+
+        i
+    };
+
+    while i < chars.len() {
+        let next = chars.get(i + 1).copied();
+
+        match chars[i] {
+            '/' if next == Some('/') => {
+                i = skip_to(&mut stripped, i, &|i| chars[i] == '\n');
+            }
+            '/' if next == Some('*') => {
+                let start = i + 2;
+
+                i = skip_to(&mut stripped, start, &|i| {
+                    i > start && chars[i] == '/' && chars[i - 1] == '*'
+                }) + 1;
+            }
+            '"' => {
+                let quotes = chars[i..].iter().take_while(|c| **c == '"').count();
+                let prefix: String = chars[i.saturating_sub(2)..i].iter().collect();
+
+                i = if quotes >= 3 {
+                    // Raw strings end at the same number of quotes
+                    let closing = &chars[i..i + quotes];
+                    skip_to(&mut stripped, i + quotes, &|i| {
+                        chars[i..].starts_with(closing)
+                    }) + quotes
+                } else if prefix.ends_with('@') || prefix == "@$" {
+                    // Verbatim strings escape quotes by doubling them
+                    let mut end = i + 1;
+
+                    loop {
+                        end = skip_to(&mut stripped, end, &|i| chars[i] == '"');
+
+                        if chars.get(end + 1) == Some(&'"') {
+                            end += 2;
+                        } else {
+                            break end + 1;
+                        }
+                    }
+                } else {
+                    skip_to(&mut stripped, i + 1, &|i| {
+                        (chars[i] == '"' && !is_escaped(&chars, i)) || chars[i] == '\n'
+                    }) + 1
+                };
+
+                stripped.push_str("\"\"");
+            }
+            '\'' => {
+                i = skip_to(&mut stripped, i + 1, &|i| {
+                    (chars[i] == '\'' && !is_escaped(&chars, i)) || chars[i] == '\n'
+                }) + 1;
+
+                stripped.push_str("''");
+            }
+            c => {
+                stripped.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    stripped
+}
+
+/// Whether the character at the given index is escaped by an odd number of
+/// backslashes
+fn is_escaped(chars: &[char], index: usize) -> bool {
+    chars[..index]
+        .iter()
+        .rev()
+        .take_while(|c| **c == '\\')
+        .count()
+        % 2
+        == 1
+}
+
+/// Top-level folders of `Assets` whose scripts are compiled before all others
+const FIRSTPASS_FOLDERS: &[&str] = &["Plugins", "Standard Assets", "Pro Standard Assets"];
+
+#[derive(Debug, Deserialize)]
+struct AssemblyDefinition {
+    name: String,
+}
+
+/// An assembly definition reference (`.asmref`), which adds the scripts of its
+/// folder to another assembly
+#[derive(Debug, Deserialize)]
+struct AssemblyDefinitionReference {
+    /// Either `GUID:<guid>` of the assembly definition, or its name
+    reference: String,
+}
+
+/// What the assembly definition file in a folder, if any, declares
+#[derive(Debug, Clone, PartialEq)]
+enum FolderAssembly {
+    Definition(String),
+    Reference(String),
+}
+
+/// Whether the given file is an assembly definition or a reference to one
+pub fn is_assembly_file(file: &Path) -> bool {
+    file.extension()
+        .is_some_and(|ext| ext == "asmdef" || ext == "asmref")
+}
+
+/// The assembly definition files of every folder looked at so far, so every
+/// folder is only read once. Entries are forgotten when the assembly files in
+/// their folder change.
+#[derive(Debug, Default)]
+pub struct AssemblyDefinitions {
+    folders: Mutex<HashMap<PathBuf, Option<FolderAssembly>>>,
+}
+
+impl AssemblyDefinitions {
+    /// Finds the name of the assembly a script is compiled into. That is the
+    /// closest assembly definition or reference in the folder of the script or
+    /// above it, or one of the predefined `Assembly-CSharp` assemblies
+    /// otherwise. References by GUID are looked up in the given assets.
+    pub async fn script_assembly(&self, script: &Path, assets: &RwLock<AssetTable>) -> String {
+        for dir in script.ancestors().skip(1) {
+            match self.folder_assembly(dir).await {
+                Some(FolderAssembly::Definition(name)) => return name,
+                Some(FolderAssembly::Reference(reference)) => {
+                    if let Some(name) = self.referenced_assembly(&reference, assets).await {
+                        return name;
+                    }
+
+                    log::debug!(
+                        "Could not find assembly definition {} referenced from {}",
+                        reference,
+                        dir.to_string_lossy()
+                    );
+                }
+                None => (),
+            }
+
+            // Assembly definitions never apply across the Assets folder or package
+            if dir.file_name().is_some_and(|name| name == "Assets")
+                || dir.join("package.json").is_file()
+            {
+                break;
+            }
+        }
+
+        predefined_assembly(script).to_owned()
+    }
+
+    /// Forgets the assembly files of the folder of a changed assembly file
+    pub fn forget(&self, file: &Path) {
+        if let Some(dir) = file.parent() {
+            self.folders.lock().unwrap().remove(dir);
+        }
+    }
+
+    pub fn clear(&self) {
+        self.folders.lock().unwrap().clear();
+    }
+
+    async fn referenced_assembly(
+        &self,
+        reference: &str,
+        assets: &RwLock<AssetTable>,
+    ) -> Option<String> {
+        let Some(guid) = reference.strip_prefix("GUID:") else {
+            // Assembly definitions are referenced by the name of their assembly
+            return Some(reference.to_owned());
+        };
+
+        let definition = assets.read().await.path(guid)?.to_path_buf();
+
+        match self.folder_assembly(definition.parent()?).await? {
+            FolderAssembly::Definition(name) => Some(name),
+            FolderAssembly::Reference(_) => None,
+        }
+    }
+
+    async fn folder_assembly(&self, dir: &Path) -> Option<FolderAssembly> {
+        if let Some(known) = self.folders.lock().unwrap().get(dir) {
+            return known.clone();
+        }
+
+        let found = read_folder_assembly(dir).await;
+
+        self.folders
+            .lock()
+            .unwrap()
+            .insert(dir.to_path_buf(), found.clone());
+
+        found
+    }
+}
+
+/// The assembly Unity compiles scripts into that are not covered by an
+/// assembly definition
+fn predefined_assembly(script: &Path) -> &'static str {
+    let in_assets: Vec<_> = script
+        .components()
+        .skip_while(|c| c.as_os_str() != "Assets")
+        .skip(1)
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+
+    let firstpass = in_assets
+        .first()
+        .is_some_and(|top| FIRSTPASS_FOLDERS.contains(&top.as_ref()));
+
+    let editor = in_assets.iter().any(|c| c == "Editor");
+
+    match (editor, firstpass) {
+        (false, false) => "Assembly-CSharp",
+        (true, false) => "Assembly-CSharp-Editor",
+        (false, true) => "Assembly-CSharp-firstpass",
+        (true, true) => "Assembly-CSharp-Editor-firstpass",
+    }
+}
+
+async fn read_folder_assembly(dir: &Path) -> Option<FolderAssembly> {
+    let mut entries = tokio::fs::read_dir(dir).await.ok()?;
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+
+        let parsed = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("asmdef") => tokio::fs::read(&path).await.ok().map(|content| {
+                serde_json::from_slice::<AssemblyDefinition>(&content)
+                    .map(|definition| FolderAssembly::Definition(definition.name))
+            }),
+            Some("asmref") => tokio::fs::read(&path).await.ok().map(|content| {
+                serde_json::from_slice::<AssemblyDefinitionReference>(&content)
+                    .map(|reference| FolderAssembly::Reference(reference.reference))
+            }),
+            _ => continue,
+        };
+
+        return match parsed? {
+            Ok(assembly) => Some(assembly),
+            Err(e) => {
+                log::warn!(
+                    "Could not parse assembly definition {}: {}",
+                    path.to_string_lossy(),
+                    e
+                );
+                None
+            }
+        };
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefers_class_named_after_file() {
+        let script = "namespace Game.UI\n{\n    class Helper {}\n    public class Menu : MonoBehaviour {}\n}\n";
+
+        assert_eq!(
+            parse_script_class(script, "Menu").as_deref(),
+            Some("Game.UI.Menu")
+        );
+    }
+
+    #[test]
+    fn ignores_classes_in_comments_and_strings() {
+        let script = r#"
+// class Commented
+/* class
+   Block */
+public class Menu : MonoBehaviour
+{
+    string text = "class Quoted";
+    string path = @"C:\class ""Verbatim""";
+    char quote = '"';
+}
+"#;
+
+        assert_eq!(parse_script_class(script, "Other").as_deref(), Some("Menu"));
+    }
+
+    #[test]
+    fn ignores_class_constraints() {
+        let script = "public static class Pool<T>\n    where T : class\n{\n}\n\n[Serializable] internal sealed class Entry {}\n";
+
+        assert_eq!(
+            parse_script_class(script, "Entry").as_deref(),
+            Some("Entry")
+        );
+        assert_eq!(parse_script_class(script, "Other").as_deref(), Some("Pool"));
+    }
+
+    #[test]
+    fn keeps_lines_when_stripping() {
+        let stripped = strip_comments_and_strings("a /* b\nc */ d\n\"e\\\"\" // f\ng");
+
+        assert_eq!(stripped, "a \n d\n\"\" \ng");
+    }
+
+    #[tokio::test]
+    async fn follows_assembly_definition_references() {
+        let assets_dir = crate::crawler::tests::test_dir("assembly-references").join("Assets");
+
+        let files = [
+            ("Core/Core.asmdef", r#"{ "name": "Game.Core" }"#),
+            ("Core/Menu.cs", ""),
+            (
+                "ByGuid/Core.asmref",
+                r#"{ "reference": "GUID:0123456789abcdef0123456789abcdef" }"#,
+            ),
+            ("ByGuid/Nested/Door.cs", ""),
+            ("ByName/Core.asmref", r#"{ "reference": "Game.Tools" }"#),
+            ("ByName/Tool.cs", ""),
+            ("Plugins/Editor/Importer.cs", ""),
+        ];
+
+        for (file, content) in files {
+            let path = assets_dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+
+        let assets = RwLock::new(AssetTable::default());
+        assets.write().await.insert(
+            "0123456789abcdef0123456789abcdef".to_owned(),
+            assets_dir.join("Core/Core.asmdef"),
+            false,
+        );
+
+        let assemblies = AssemblyDefinitions::default();
+
+        let expected = [
+            ("Core/Menu.cs", "Game.Core"),
+            ("ByGuid/Nested/Door.cs", "Game.Core"),
+            ("ByName/Tool.cs", "Game.Tools"),
+            (
+                "Plugins/Editor/Importer.cs",
+                "Assembly-CSharp-Editor-firstpass",
+            ),
+        ];
+
+        for (script, assembly) in expected {
+            assert_eq!(
+                assemblies
+                    .script_assembly(&assets_dir.join(script), &assets)
+                    .await,
+                assembly
+            );
+        }
+
+        // Changes are only seen once the folder is forgotten
+        let definition = assets_dir.join("Core/Core.asmdef");
+        std::fs::write(&definition, r#"{ "name": "Game.Renamed" }"#).unwrap();
+
+        let door = assets_dir.join("ByGuid/Nested/Door.cs");
+        assert_eq!(
+            assemblies.script_assembly(&door, &assets).await,
+            "Game.Core"
+        );
+
+        assemblies.forget(&definition);
+        assert_eq!(
+            assemblies.script_assembly(&door, &assets).await,
+            "Game.Renamed"
+        );
+
+        _ = std::fs::remove_dir_all(assets_dir.parent().unwrap());
+    }
+}
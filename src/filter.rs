@@ -0,0 +1,188 @@
+use std::path::{Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+
+/// Folders of a Unity project that never contain assets, relative to the
+/// project root. Unity itself also skips hidden files and folders, and those
+/// ending in `~`.
+const DEFAULT_EXCLUDES: &[&str] = &[
+    "/Library/",
+    "/Temp/",
+    "/Logs/",
+    "/obj/",
+    "/UserSettings/",
+    "/Build/",
+    "/Builds/",
+    ".*",
+    "*~",
+];
+
+/// Packages are crawled as their own roots, skipping only the paths Unity skips
+const PACKAGE_EXCLUDES: &[&str] = &[".*", "*~"];
+
+/// Decides which paths are crawled and watched. Excludes and includes use
+/// `.gitignore` syntax, with includes taking precedence over everything else.
+#[derive(Debug)]
+pub struct PathFilter {
+    root: PathBuf,
+    excludes: Gitignore,
+    includes: Gitignore,
+    include_patterns: Vec<String>,
+    gitignores: Vec<Gitignore>,
+    packages: Vec<Gitignore>,
+}
+
+impl PathFilter {
+    pub fn new(
+        root: &Path,
+        excludes: &[String],
+        includes: &[String],
+        use_gitignore: bool,
+    ) -> Result<Self, ignore::Error> {
+        // The given folder can be either the project root or its Assets folder
+        let project_root = if root.file_name().is_some_and(|name| name == "Assets") {
+            root.parent().unwrap_or(root)
+        } else {
+            root
+        };
+
+        let mut exclude_builder = GitignoreBuilder::new(project_root);
+
+        for exclude in DEFAULT_EXCLUDES {
+            exclude_builder.add_line(None, exclude)?;
+        }
+
+        for exclude in excludes {
+            exclude_builder.add_line(None, exclude)?;
+        }
+
+        let mut include_builder = GitignoreBuilder::new(project_root);
+
+        for include in includes {
+            include_builder.add_line(None, include)?;
+        }
+
+        // The repository usually starts at the project root, but some keep
+        // only the Assets folder under version control
+        let gitignores = if use_gitignore {
+            [project_root.to_path_buf(), project_root.join("Assets")]
+                .into_iter()
+                .map(|dir| dir.join(".gitignore"))
+                .filter(|gitignore| gitignore.is_file())
+                .filter_map(|gitignore| {
+                    let (matcher, error) = Gitignore::new(&gitignore);
+
+                    if let Some(e) = error {
+                        log::warn!("Error in {}: {}", gitignore.to_string_lossy(), e);
+                    }
+
+                    log::debug!("Using ignore rules from {}", gitignore.to_string_lossy());
+
+                    (!matcher.is_empty()).then_some(matcher)
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            root: project_root.to_path_buf(),
+            excludes: exclude_builder.build()?,
+            includes: include_builder.build()?,
+            include_patterns: includes
+                .iter()
+                .map(|include| include.trim_start_matches('/').to_owned())
+                .collect(),
+            gitignores,
+            packages: Vec::new(),
+        })
+    }
+
+    /// Adds the root of a package. Paths within it are only checked against the
+    /// package excludes, as packages are commonly stored in excluded folders
+    /// such as `Library/PackageCache`.
+    pub fn add_package_root(&mut self, root: &Path) -> Result<(), ignore::Error> {
+        let mut builder = GitignoreBuilder::new(root);
+
+        for exclude in PACKAGE_EXCLUDES {
+            builder.add_line(None, exclude)?;
+        }
+
+        self.packages.push(builder.build()?);
+
+        Ok(())
+    }
+
+    /// Whether a path should be skipped. For files, all parent folders up to
+    /// the root are checked as well.
+    pub fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        if let Some(package) = self
+            .packages
+            .iter()
+            .find(|package| path.starts_with(package.path()))
+        {
+            return matches_path_or_parents(package, path, is_dir);
+        }
+
+        if !path.starts_with(&self.root) {
+            return false;
+        }
+
+        if matches_path_or_parents(&self.includes, path, is_dir) {
+            return false;
+        }
+
+        // Folders that contain an explicitly included path still have to be
+        // crawled to reach it
+        if is_dir && self.contains_include(path) {
+            return false;
+        }
+
+        matches_path_or_parents(&self.excludes, path, is_dir)
+            || self
+                .gitignores
+                .iter()
+                .any(|gitignore| matches_path_or_parents(gitignore, path, is_dir))
+    }
+
+    fn contains_include(&self, dir: &Path) -> bool {
+        let Ok(relative) = dir.strip_prefix(&self.root) else {
+            return false;
+        };
+
+        let relative = relative.to_string_lossy().replace('\\', "/");
+
+        self.include_patterns
+            .iter()
+            .any(|pattern| pattern.starts_with(&format!("{}/", relative)))
+    }
+}
+
+fn matches_path_or_parents(matcher: &Gitignore, path: &Path, is_dir: bool) -> bool {
+    if !path.starts_with(matcher.path()) {
+        return false;
+    }
+
+    matches!(
+        matcher.matched_path_or_any_parents(path, is_dir),
+        Match::Ignore(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_excluded_assets_with_their_meta_files() {
+        let root = Path::new("/project");
+        let filter = PathFilter::new(root, &["/Assets/Third/".to_owned()], &[], false).unwrap();
+
+        assert!(filter.is_excluded(Path::new("/project/Assets/Third"), true));
+        assert!(filter.is_excluded(Path::new("/project/Assets/Third/Mat.mat"), false));
+        assert!(filter.is_excluded(Path::new("/project/Assets/Third/Mat.mat.meta"), false));
+        assert!(!filter.is_excluded(Path::new("/project/Assets/Mat.mat.meta"), false));
+        assert!(filter.is_excluded(Path::new("/project/Library/Mat.mat.meta"), false));
+    }
+}
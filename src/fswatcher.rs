@@ -1,18 +1,20 @@
-use notify::{Config, Event, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use notify::{Config, Event, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::sync::mpsc::{channel, Receiver};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::runtime::Handle;
 
 use crate::crawler::Crawler;
 
 /// Async, futures channel based event watching
-pub fn start_watch(crawler: Arc<Crawler>, path: &Path, runtime: Handle) {
-    let buf = path.to_path_buf();
-    std::thread::spawn(|| watch(crawler, buf, runtime));
+pub fn start_watch(crawler: Arc<Crawler>, paths: &[PathBuf], runtime: Handle, debounce: Duration) {
+    let paths = paths.to_vec();
+    std::thread::spawn(move || watch(crawler, paths, runtime, debounce));
 }
 
-fn watch(crawler: Arc<Crawler>, path: PathBuf, runtime: Handle) {
+fn watch(crawler: Arc<Crawler>, paths: Vec<PathBuf>, runtime: Handle, debounce: Duration) {
     let (mut watcher, rx) = match make_watcher() {
         Ok((w, rx)) => (w, rx),
         Err(e) => {
@@ -24,39 +26,177 @@ fn watch(crawler: Arc<Crawler>, path: PathBuf, runtime: Handle) {
         }
     };
 
-    // Add a path to be watched. All files and directories at that path and
-    // below will be monitored for changes.
-    if let Err(e) = watcher.watch(path.as_ref(), RecursiveMode::Recursive) {
-        log::error!(
-            "Error starting the constructed filesystem watcher, no changes will be processed: {}",
-            e
-        );
-        return;
-    };
+    // Excluded folders such as Library hold many files that change all the
+    // time, so instead of watching the paths recursively, every folder below
+    // them that is not excluded is watched on its own
+    let mut watched_dirs = HashSet::new();
+
+    for path in &paths {
+        watch_tree(watcher.as_mut(), &crawler, path, &mut watched_dirs);
+    }
+
+    // Unity saves through temp files, renames and multiple writes, so every
+    // changed path is only handled once it has been quiet for the debounce
+    // window. The value is the time the path was last seen in an event.
+    let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+
+    // Watched folders that were removed or renamed while pending. Their files
+    // are not reported one by one, so they are dropped from the index as a
+    // whole once settled.
+    let mut removed_dirs: HashSet<PathBuf> = HashSet::new();
 
-    for res in rx {
-        match res {
-            Ok(event) => {
+    loop {
+        let received = match pending.values().min() {
+            Some(oldest) => {
+                let deadline = *oldest + debounce;
+                rx.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            }
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match received {
+            Ok(Ok(event)) => {
                 log::trace!("Filesystem event: {:#?}", event);
 
-                let cloned_crawler = crawler.clone();
+                if is_relevant_event(&event.kind) {
+                    let now = Instant::now();
+
+                    for changed in event.paths {
+                        let is_dir = changed.is_dir();
+
+                        if crawler.filter.is_excluded(&changed, is_dir) {
+                            continue;
+                        }
 
-                let handle = runtime.spawn(async {
-                    handle_event(cloned_crawler, event).await;
-                });
+                        // Watches of removed folders are dropped along with them
+                        if !is_dir && watched_dirs.remove(&changed) {
+                            watched_dirs.retain(|dir| !dir.starts_with(&changed));
+                            removed_dirs.insert(changed.clone());
+                        }
 
-                futures::executor::block_on(async {
-                    handle.await.unwrap();
-                });
+                        // Folders created or moved in are not covered by the
+                        // watch of their parent, and may already contain files
+                        if is_dir && !watched_dirs.contains(&changed) {
+                            for file in
+                                watch_tree(watcher.as_mut(), &crawler, &changed, &mut watched_dirs)
+                            {
+                                pending.insert(file, now);
+                            }
+                        }
+
+                        pending.insert(changed, now);
+                    }
+                }
             }
-            Err(e) => {
+            Ok(Err(e)) => {
                 log::warn!("Filesystem watch error: {}", e);
             }
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => {
+                log::warn!("Filesystem watcher stopped, no more changes will be processed");
+                return;
+            }
+        }
+
+        let settled = take_settled(&mut pending, debounce);
+
+        if settled.is_empty() {
+            continue;
+        }
+
+        log::debug!("Processing {} changed path(s)", settled.len());
+
+        let (removed, changed): (Vec<_>, Vec<_>) = settled
+            .into_iter()
+            .partition(|path| removed_dirs.remove(path));
+
+        let cloned_crawler = crawler.clone();
+
+        let handle = runtime.spawn(async {
+            handle_changed_paths(cloned_crawler, removed, changed).await;
+        });
+
+        futures::executor::block_on(async {
+            handle.await.unwrap();
+        });
+    }
+}
+
+/// Watches a folder and every folder below it that is not excluded, returning
+/// the files found in them. Packages are skipped, as they are watched as their
+/// own root.
+fn watch_tree(
+    watcher: &mut dyn Watcher,
+    crawler: &Crawler,
+    root: &Path,
+    watched_dirs: &mut HashSet<PathBuf>,
+) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+            log::error!(
+                "Error watching {}, no changes there will be processed: {}",
+                dir.to_string_lossy(),
+                e
+            );
+            continue;
+        }
+
+        watched_dirs.insert(dir.clone());
+
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::warn!("Could not read {}: {}", dir.to_string_lossy(), e);
+                continue;
+            }
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+
+            if crawler.filter.is_excluded(&path, file_type.is_dir()) {
+                continue;
+            }
+
+            if file_type.is_dir() {
+                if !crawler.packages.iter().any(|p| p.path == path) {
+                    dirs.push(path);
+                }
+            } else if file_type.is_file() {
+                files.push(path);
+            }
         }
     }
+
+    files
 }
 
-fn make_watcher() -> notify::Result<(Box<dyn Watcher>, Receiver<notify::Result<Event>>)> {
+fn take_settled(pending: &mut HashMap<PathBuf, Instant>, debounce: Duration) -> Vec<PathBuf> {
+    let now = Instant::now();
+    let mut settled = Vec::new();
+
+    pending.retain(|path, last_seen| {
+        if now.duration_since(*last_seen) >= debounce {
+            settled.push(path.clone());
+            false
+        } else {
+            true
+        }
+    });
+
+    settled
+}
+
+type EventReceiver = Receiver<notify::Result<Event>>;
+
+fn make_watcher() -> notify::Result<(Box<dyn Watcher>, EventReceiver)> {
     let (tx, rx) = channel();
 
     let config = Config::default();
@@ -81,4 +221,65 @@ fn make_watcher() -> notify::Result<(Box<dyn Watcher>, Receiver<notify::Result<E
     Ok((watcher, rx))
 }
 
-async fn handle_event(crawler: Arc<Crawler>, event: notify::Event) {}
+fn is_relevant_event(kind: &EventKind) -> bool {
+    matches!(
+        kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+    )
+}
+
+// Renames report both the old and the new path, so re-indexing every reported
+// path handles them the same as a remove followed by a create
+async fn handle_changed_paths(
+    crawler: Arc<Crawler>,
+    removed_dirs: Vec<PathBuf>,
+    paths: Vec<PathBuf>,
+) {
+    for dir in &removed_dirs {
+        crawler.remove_folder(dir).await;
+    }
+
+    let futures: Vec<_> = paths
+        .iter()
+        .map(|path| crawler.reindex_file(path))
+        .collect();
+
+    futures::future::join_all(futures).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEBOUNCE: Duration = Duration::from_millis(500);
+
+    #[test]
+    fn keeps_paths_seen_within_the_debounce_window() {
+        let path = PathBuf::from("/project/Assets/Level.unity");
+        let mut pending = HashMap::from([(path.clone(), Instant::now())]);
+
+        assert!(take_settled(&mut pending, DEBOUNCE).is_empty());
+
+        // Seeing the path again restarts its window
+        let seen_again = Instant::now();
+        pending.insert(path.clone(), seen_again);
+
+        assert!(take_settled(&mut pending, DEBOUNCE).is_empty());
+        assert_eq!(pending.get(&path), Some(&seen_again));
+    }
+
+    #[test]
+    fn returns_settled_paths() {
+        let settled = PathBuf::from("/project/Assets/Level.unity");
+        let recent = PathBuf::from("/project/Assets/Menu.prefab");
+        let quiet_since = Instant::now() - DEBOUNCE * 2;
+
+        let mut pending = HashMap::from([
+            (settled.clone(), quiet_since),
+            (recent.clone(), Instant::now()),
+        ]);
+
+        assert_eq!(take_settled(&mut pending, DEBOUNCE), vec![settled]);
+        assert_eq!(pending.keys().collect::<Vec<_>>(), vec![&recent]);
+    }
+}
@@ -0,0 +1,349 @@
+use std::collections::{HashMap, HashSet};
+
+use saphyr::{MarkedYaml, YamlData};
+
+use crate::unityyaml::{UnityDocument, GAME_OBJECT_CLASS_ID, PREFAB_INSTANCE_CLASS_ID};
+use crate::yamlparser::{yaml_file_id, yaml_get, yaml_to_guid, yaml_to_string};
+
+/// The GameObject hierarchy of a single scene or prefab, used to turn the
+/// file-local IDs of objects into human readable paths. Prefab instances take
+/// the place of the root GameObject of their prefab.
+#[derive(Debug, Clone, Default)]
+pub struct FileHierarchy {
+    /// GameObject and prefab instance file IDs to their names
+    names: HashMap<i64, String>,
+
+    /// Component file IDs to the file ID of the GameObject they are attached to
+    owners: HashMap<i64, i64>,
+
+    /// GameObject file IDs to the file ID of their parent GameObject
+    parents: HashMap<i64, i64>,
+
+    /// MonoBehaviour file IDs to the GUID of their script
+    scripts: HashMap<i64, String>,
+}
+
+/// The root GameObject of a prefab
+#[derive(Debug, Clone)]
+pub struct PrefabRoot {
+    pub file_id: i64,
+    pub name: Option<String>,
+}
+
+impl FileHierarchy {
+    /// Builds the hierarchy of a file. The roots of the source prefabs are only
+    /// needed for the instances returned by [`ambiguous_instance_names`].
+    pub fn from_documents(
+        docs: &[UnityDocument],
+        prefab_roots: &HashMap<String, PrefabRoot>,
+    ) -> Self {
+        let mut hierarchy = Self::default();
+
+        // Transform file IDs to their GameObject and their parent Transform
+        let mut transforms: HashMap<i64, (i64, i64)> = HashMap::new();
+
+        // Stripped stand-ins for objects of a prefab instance to that instance
+        let mut stripped: HashMap<i64, i64> = HashMap::new();
+
+        // Prefab instance file IDs to the Transform they are parented to
+        let mut instances: HashMap<i64, i64> = HashMap::new();
+
+        for doc in docs {
+            let Some(file_id) = doc.file_id else {
+                continue;
+            };
+
+            let Some((class, object)) = doc.yaml.data.as_hash().and_then(|h| h.front()) else {
+                continue;
+            };
+
+            if doc.stripped {
+                if let Some(instance) = yaml_get(object, "m_PrefabInstance").and_then(yaml_file_id)
+                {
+                    stripped.insert(file_id, instance);
+                }
+
+                continue;
+            }
+
+            if is_prefab_instance(doc, class) {
+                if let Some(modification) = yaml_get(object, "m_Modification") {
+                    let root = source_prefab(object).and_then(|guid| prefab_roots.get(&guid));
+
+                    if let Some(name) = modified_name(modification, root) {
+                        hierarchy.names.insert(file_id, name);
+                    }
+
+                    if let Some(parent) =
+                        yaml_get(modification, "m_TransformParent").and_then(yaml_file_id)
+                    {
+                        instances.insert(file_id, parent);
+                    }
+                }
+
+                continue;
+            }
+
+            let is_game_object = match doc.class_id {
+                Some(class_id) => class_id == GAME_OBJECT_CLASS_ID,
+                None => class.data.as_str() == Some("GameObject"),
+            };
+
+            if is_game_object {
+                if let Some(name) = yaml_get(object, "m_Name").and_then(yaml_to_string) {
+                    hierarchy.names.insert(file_id, name);
+                }
+
+                continue;
+            }
+
+            let Some(game_object) = yaml_get(object, "m_GameObject").and_then(yaml_file_id) else {
+                continue;
+            };
+
+            hierarchy.owners.insert(file_id, game_object);
+
+            if let Some(guid) = yaml_get(object, "m_Script")
+                .and_then(|script| yaml_get(script, "guid"))
+                .and_then(yaml_to_guid)
+            {
+                hierarchy.scripts.insert(file_id, guid);
+            }
+
+            // Both Transform and RectTransform store the hierarchy
+            if let Some(father) = yaml_get(object, "m_Father").and_then(yaml_file_id) {
+                transforms.insert(file_id, (game_object, father));
+            }
+        }
+
+        // Objects attached to or parented to an object of a prefab instance
+        // refer to its stripped stand-in, which is represented by the instance
+        for owner in hierarchy.owners.values_mut() {
+            *owner = stripped.get(owner).copied().unwrap_or(*owner);
+        }
+
+        let parent_of = |transform: &i64| {
+            transforms
+                .get(transform)
+                .map(|(parent_object, _)| *parent_object)
+                .or_else(|| stripped.get(transform).copied())
+        };
+
+        for (game_object, father) in transforms.values() {
+            if let Some(parent_object) = parent_of(father) {
+                hierarchy.parents.insert(*game_object, parent_object);
+            }
+        }
+
+        for (instance, parent) in &instances {
+            if let Some(parent_object) = parent_of(parent) {
+                hierarchy.parents.insert(*instance, parent_object);
+            }
+        }
+
+        hierarchy
+    }
+
+    /// Returns the GUID of the script of the MonoBehaviour with the given file ID
+    pub fn script(&self, file_id: i64) -> Option<&str> {
+        self.scripts.get(&file_id).map(String::as_str)
+    }
+
+    /// Returns the path from the root GameObject to the GameObject with the
+    /// given file ID, or to the GameObject owning the component with the given
+    /// file ID
+    pub fn object_path(&self, file_id: i64) -> Option<Vec<String>> {
+        let mut current = if self.names.contains_key(&file_id) {
+            file_id
+        } else {
+            *self.owners.get(&file_id)?
+        };
+
+        let mut path = vec![self.names.get(&current)?.clone()];
+
+        while let Some(parent) = self.parents.get(&current) {
+            // Guard against malformed files with cyclic parents
+            if path.len() > self.parents.len() {
+                log::warn!("Cyclic GameObject hierarchy found at object {}", file_id);
+                break;
+            }
+
+            let Some(parent_name) = self.names.get(parent) else {
+                break;
+            };
+
+            path.push(parent_name.clone());
+            current = *parent;
+        }
+
+        path.reverse();
+
+        Some(path)
+    }
+}
+
+fn is_prefab_instance(doc: &UnityDocument, class: &MarkedYaml) -> bool {
+    match doc.class_id {
+        Some(class_id) => class_id == PREFAB_INSTANCE_CLASS_ID,
+        None => class.data.as_str() == Some("PrefabInstance"),
+    }
+}
+
+fn source_prefab(instance: &MarkedYaml) -> Option<String> {
+    yaml_get(instance, "m_SourcePrefab")
+        .and_then(|source| yaml_get(source, "guid"))
+        .and_then(yaml_to_guid)
+}
+
+/// Finds the source prefabs of the prefab instances whose name cannot be told
+/// apart from the names of the other objects they override
+pub fn ambiguous_instance_names(docs: &[UnityDocument]) -> HashSet<String> {
+    docs.iter()
+        .filter(|doc| !doc.stripped)
+        .filter_map(|doc| {
+            let (class, object) = doc.yaml.data.as_hash()?.front()?;
+
+            if !is_prefab_instance(doc, class) {
+                return None;
+            }
+
+            let modification = yaml_get(object, "m_Modification")?;
+
+            (name_overrides(modification).len() > 1)
+                .then(|| source_prefab(object))
+                .flatten()
+        })
+        .collect()
+}
+
+/// Finds the root GameObject of a prefab, which is the one whose Transform has
+/// no parent
+pub fn find_prefab_root(docs: &[UnityDocument]) -> Option<PrefabRoot> {
+    let objects = || {
+        docs.iter()
+            .filter(|doc| !doc.stripped)
+            .filter_map(|doc| Some((doc.file_id, doc.yaml.data.as_hash()?.front()?.1)))
+    };
+
+    let file_id = objects()
+        .map(|(_, object)| object)
+        .find(|object| yaml_get(object, "m_Father").and_then(yaml_file_id) == Some(0))
+        .and_then(|transform| yaml_get(transform, "m_GameObject"))
+        .and_then(yaml_file_id)?;
+
+    let name = objects()
+        .find(|(id, _)| *id == Some(file_id))
+        .and_then(|(_, object)| yaml_get(object, "m_Name"))
+        .and_then(yaml_to_string);
+
+    Some(PrefabRoot { file_id, name })
+}
+
+/// Finds the name a prefab instance is given, which is stored as an override of
+/// the `m_Name` of its root GameObject. Without an override, the instance has
+/// the name of the root itself.
+fn modified_name(modification: &MarkedYaml, root: Option<&PrefabRoot>) -> Option<String> {
+    let mut overrides = name_overrides(modification);
+
+    match root {
+        Some(root) => overrides
+            .into_iter()
+            .find(|(target, _)| *target == Some(root.file_id))
+            .map(|(_, name)| name)
+            .or_else(|| root.name.clone()),
+
+        // Unity always overrides the name of the root, so a single override
+        // is that of the root. Renamed objects within the instance add more.
+        None if overrides.len() == 1 => overrides.pop().map(|(_, name)| name),
+        None => None,
+    }
+}
+
+/// The `m_Name` overrides of a prefab instance, along with the file ID of the
+/// object in the source prefab they apply to
+fn name_overrides(modification: &MarkedYaml) -> Vec<(Option<i64>, String)> {
+    let Some(YamlData::Array(modifications)) =
+        yaml_get(modification, "m_Modifications").map(|m| &m.data)
+    else {
+        return Vec::new();
+    };
+
+    modifications
+        .iter()
+        .filter(|m| yaml_get(m, "propertyPath").and_then(|p| p.data.as_str()) == Some("m_Name"))
+        .filter_map(|m| {
+            let target = yaml_get(m, "target").and_then(yaml_file_id);
+            let name = yaml_get(m, "value").and_then(yaml_to_string)?;
+
+            Some((target, name))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unityyaml::load_unity_yaml;
+
+    const SOURCE: &str = "5ab2d8c3e1f04a7b9c6d2e8f1a3b5c7d";
+
+    fn instance(modifications: &str) -> String {
+        format!(
+            "--- !u!1001 &10\nPrefabInstance:\n  m_Modification:\n    m_TransformParent: {{fileID: 0}}\n    m_Modifications:\n{}  m_SourcePrefab: {{fileID: 100100000, guid: {}, type: 3}}\n",
+            modifications, SOURCE
+        )
+    }
+
+    fn name_override(target: i64, name: &str) -> String {
+        format!(
+            "    - target: {{fileID: {}, guid: {}, type: 3}}\n      propertyPath: m_Name\n      value: {}\n      objectReference: {{fileID: 0}}\n",
+            target, SOURCE, name
+        )
+    }
+
+    #[test]
+    fn names_instances_by_their_only_override() {
+        let docs = load_unity_yaml(&instance(&name_override(1, "Door (1)"))).unwrap();
+
+        assert!(ambiguous_instance_names(&docs).is_empty());
+
+        let hierarchy = FileHierarchy::from_documents(&docs, &HashMap::new());
+        assert_eq!(hierarchy.object_path(10), Some(vec!["Door (1)".to_owned()]));
+    }
+
+    #[test]
+    fn names_instances_by_the_override_of_their_root() {
+        let modifications = name_override(2, "Handle") + &name_override(1, "Door (1)");
+        let docs = load_unity_yaml(&instance(&modifications)).unwrap();
+
+        assert_eq!(
+            ambiguous_instance_names(&docs),
+            HashSet::from([SOURCE.to_owned()])
+        );
+
+        let without_root = FileHierarchy::from_documents(&docs, &HashMap::new());
+        assert_eq!(without_root.object_path(10), None);
+
+        let root = PrefabRoot {
+            file_id: 1,
+            name: Some("Door".to_owned()),
+        };
+        let roots = HashMap::from([(SOURCE.to_owned(), root)]);
+
+        let hierarchy = FileHierarchy::from_documents(&docs, &roots);
+        assert_eq!(hierarchy.object_path(10), Some(vec!["Door (1)".to_owned()]));
+    }
+
+    #[test]
+    fn finds_prefab_roots() {
+        let docs = load_unity_yaml(
+            "--- !u!1 &1\nGameObject:\n  m_Name: Door\n--- !u!4 &2\nTransform:\n  m_GameObject: {fileID: 1}\n  m_Father: {fileID: 0}\n--- !u!1 &3\nGameObject:\n  m_Name: Handle\n--- !u!4 &4\nTransform:\n  m_GameObject: {fileID: 3}\n  m_Father: {fileID: 2}\n",
+        )
+        .unwrap();
+
+        let root = find_prefab_root(&docs).unwrap();
+
+        assert_eq!(root.file_id, 1);
+        assert_eq!(root.name.as_deref(), Some("Door"));
+    }
+}
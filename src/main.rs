@@ -1,22 +1,48 @@
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
-use api::method::rpc_method_handler;
-use api::status::rpc_status_handler;
+use api::asset::{rpc_guid_to_path_handler, rpc_path_to_guid_handler};
+use api::component::rpc_component_usages_handler;
+use api::dependencies::rpc_dependencies_handler;
+use api::diagnostics::rpc_diagnostics_handler;
+use api::errors::rpc_errors_handler;
+use api::list::{rpc_list_methods_handler, rpc_list_types_handler};
+use api::method::{rpc_method_handler, rpc_methods_batch_handler};
+use api::object::rpc_object_handler;
+use api::prefab::rpc_prefab_instances_handler;
+use api::search::rpc_search_methods_handler;
+use api::status::{rpc_retry_handler, rpc_status_handler, StatusResponse};
+use api::subscription::{rpc_subscribe_references_handler, rpc_subscribe_status_handler};
+use api::unused::rpc_unused_assets_handler;
 use args::CliArgs;
+use cache::cache_file_path;
 use clap::Parser;
 use crawler::Crawler;
+use filter::PathFilter;
 use fswatcher::start_watch;
 use jsonlogger::JsonLogger;
 use jsonrpsee::server::Server;
 use jsonrpsee::RpcModule;
+use packages::find_packages;
 use simplelog::{ConfigBuilder, TermLogger};
 use tokio::runtime::Handle;
+use tokio::sync::broadcast::error::RecvError;
+use unused::find_unused_assets;
 
 mod api;
 mod args;
+mod cache;
 mod crawler;
+mod csharp;
+mod filter;
 mod fswatcher;
+mod hierarchy;
 mod jsonlogger;
+mod metafile;
+mod packages;
+mod unityyaml;
+mod unused;
 mod yamlparser;
 
 #[tokio::main]
@@ -35,7 +61,87 @@ async fn main() {
         .unwrap();
     }
 
-    log::info!("Watching folder: {}", args.folder.to_string_lossy());
+    // Filesystem events report absolute paths, so make sure the crawler
+    // records those as well. A folder that does not exist is left for the
+    // crawler to report through its status.
+    let folder = args
+        .folder
+        .canonicalize()
+        .or_else(|_| std::path::absolute(&args.folder))
+        .unwrap_or_else(|_| args.folder.clone());
+
+    log::info!("Watching folder: {}", folder.to_string_lossy());
+    // Start the crawler
+    let cache_file = if args.no_cache {
+        None
+    } else {
+        cache_file_path(&folder, args.cache_dir.as_deref())
+    };
+
+    match &cache_file {
+        Some(file) => log::info!("Using index cache at {}", file.to_string_lossy()),
+        None => log::info!("Not using an index cache"),
+    }
+
+    let found_packages = find_packages(&folder);
+
+    // Without indexing packages, none of their assets are known
+    let (packages, unindexed_packages) = if args.no_packages {
+        let names = found_packages
+            .packages
+            .into_iter()
+            .map(|package| package.name)
+            .chain(found_packages.unresolved)
+            .collect();
+
+        (Vec::new(), names)
+    } else {
+        (found_packages.packages, found_packages.unresolved)
+    };
+
+    let mut filter =
+        match PathFilter::new(&folder, &args.exclude, &args.include, !args.no_gitignore) {
+            Ok(filter) => filter,
+            Err(e) => {
+                log::error!("Invalid exclude or include pattern: {}", e);
+                std::process::exit(1);
+            }
+        };
+
+    for package in &packages {
+        if let Err(e) = filter.add_package_root(&package.path) {
+            log::error!(
+                "Could not create filter for package {}: {}",
+                package.name,
+                e
+            );
+            std::process::exit(1);
+        }
+    }
+
+    // Packages are watched as their own root, as they are often stored in
+    // excluded folders such as Library/PackageCache
+    let watched: Vec<PathBuf> = std::iter::once(folder.clone())
+        .chain(packages.iter().map(|package| package.path.clone()))
+        .collect();
+
+    let crawler = Arc::new(
+        Crawler::new(
+            &folder,
+            &args.extensions,
+            filter,
+            packages,
+            unindexed_packages,
+            cache_file,
+        )
+        .await,
+    );
+
+    if args.report_unused {
+        report_unused(&crawler).await;
+        return;
+    }
+
     log::info!(
         "Starting server on address and port: {}:{}",
         args.addr,
@@ -50,12 +156,15 @@ async fn main() {
     // Print the port to stdout
     println!("{}", actual_addr.port());
 
-    // Start the crawler
-    let crawler = Arc::new(Crawler::new(&args.folder).await);
     crawler.start().await;
 
     // Watch for changes in the asset directory
-    start_watch(crawler.clone(), &args.folder, Handle::current());
+    start_watch(
+        crawler.clone(),
+        &watched,
+        Handle::current(),
+        Duration::from_millis(args.debounce_ms),
+    );
 
     // Start the JSONRPC server
     let mut module = RpcModule::from_arc(crawler);
@@ -63,13 +172,131 @@ async fn main() {
         .register_async_method("status", rpc_status_handler)
         .unwrap();
 
+    module
+        .register_async_method("errors", rpc_errors_handler)
+        .unwrap();
+
+    module
+        .register_async_method("retry", rpc_retry_handler)
+        .unwrap();
+
     module
         .register_async_method("method", rpc_method_handler)
         .unwrap();
 
+    module
+        .register_async_method("methods_batch", rpc_methods_batch_handler)
+        .unwrap();
+
+    module
+        .register_async_method("object", rpc_object_handler)
+        .unwrap();
+
+    module
+        .register_async_method("search_methods", rpc_search_methods_handler)
+        .unwrap();
+
+    module
+        .register_async_method("list_methods", rpc_list_methods_handler)
+        .unwrap();
+
+    module
+        .register_async_method("list_types", rpc_list_types_handler)
+        .unwrap();
+
+    module
+        .register_async_method("component_usages", rpc_component_usages_handler)
+        .unwrap();
+
+    module
+        .register_async_method("prefab_instances", rpc_prefab_instances_handler)
+        .unwrap();
+
+    module
+        .register_async_method("dependencies", rpc_dependencies_handler)
+        .unwrap();
+
+    module
+        .register_async_method("diagnostics", rpc_diagnostics_handler)
+        .unwrap();
+
+    module
+        .register_async_method("unused_assets", rpc_unused_assets_handler)
+        .unwrap();
+
+    module
+        .register_async_method("guid_to_path", rpc_guid_to_path_handler)
+        .unwrap();
+
+    module
+        .register_async_method("path_to_guid", rpc_path_to_guid_handler)
+        .unwrap();
+
+    module
+        .register_subscription(
+            "subscribe_status",
+            "status_changed",
+            "unsubscribe_status",
+            rpc_subscribe_status_handler,
+        )
+        .unwrap();
+
+    module
+        .register_subscription(
+            "subscribe_references",
+            "references_changed",
+            "unsubscribe_references",
+            rpc_subscribe_references_handler,
+        )
+        .unwrap();
+
     let handle = server.start(module);
 
     log::info!("Started server");
 
     tokio::spawn(handle.stopped()).await.unwrap();
 }
+
+/// Prints the unused assets of the project to stdout, one path per line, once
+/// the initial crawl is done. Assets that may be unused are printed after
+/// them, marked as unknown.
+async fn report_unused(crawler: &Crawler) {
+    let mut status_events = crawler.status_events.subscribe();
+    crawler.start().await;
+
+    loop {
+        match status_events.recv().await {
+            Ok(StatusResponse::Ready) => break,
+            Ok(StatusResponse::Error { message, .. }) => {
+                log::error!("Could not index the project: {}", message);
+                std::process::exit(1);
+            }
+            Ok(_) | Err(RecvError::Lagged(_)) => (),
+            Err(RecvError::Closed) => return,
+        }
+    }
+
+    let report = find_unused_assets(crawler).await;
+
+    for asset in &report.unused {
+        println!("{}", asset.path.to_string_lossy());
+    }
+
+    // Assets that may still be used are marked, so they are not mistaken for
+    // unused ones when the output is processed further
+    for asset in &report.unknown {
+        println!("unknown: {}", asset.path.to_string_lossy());
+    }
+
+    if !report.unparsed.is_empty() {
+        log::warn!(
+            "{} assets may be unused, but {} reachable assets are serialized in binary and may reference them:",
+            report.unknown.len(),
+            report.unparsed.len()
+        );
+
+        for asset in &report.unparsed {
+            log::warn!("{}", asset.path.to_string_lossy());
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use tokio::io;
+
+/// Bidirectional mapping between asset GUIDs and the paths of the assets they
+/// belong to, built from the `.meta` files next to each asset
+#[derive(Debug, Clone, Default)]
+pub struct AssetTable {
+    guid_to_path: HashMap<String, PathBuf>,
+    path_to_guid: HashMap<PathBuf, String>,
+
+    /// The GUIDs of the assets that are folders
+    folders: HashSet<String>,
+}
+
+impl AssetTable {
+    pub fn insert(&mut self, guid: String, asset: PathBuf, folder: bool) {
+        self.remove_path(&asset);
+
+        if folder {
+            self.folders.insert(guid.clone());
+        } else {
+            self.folders.remove(&guid);
+        }
+
+        if let Some(old_path) = self.guid_to_path.insert(guid.clone(), asset.clone()) {
+            log::warn!(
+                "GUID {} is used by both {} and {}",
+                guid,
+                old_path.to_string_lossy(),
+                asset.to_string_lossy()
+            );
+            self.path_to_guid.remove(&old_path);
+        }
+
+        self.path_to_guid.insert(asset, guid);
+    }
+
+    pub fn remove_path(&mut self, asset: &Path) {
+        if let Some(guid) = self.path_to_guid.remove(asset) {
+            self.guid_to_path.remove(&guid);
+            self.folders.remove(&guid);
+        }
+    }
+
+    /// Removes every asset inside the given folder, and the folder itself
+    pub fn remove_folder(&mut self, folder: &Path) {
+        let removed: Vec<PathBuf> = self
+            .path_to_guid
+            .keys()
+            .filter(|asset| asset.starts_with(folder))
+            .cloned()
+            .collect();
+
+        for asset in removed {
+            self.remove_path(&asset);
+        }
+    }
+
+    pub fn path(&self, guid: &str) -> Option<&Path> {
+        self.guid_to_path.get(guid).map(PathBuf::as_path)
+    }
+
+    pub fn guid(&self, asset: &Path) -> Option<&str> {
+        self.path_to_guid.get(asset).map(String::as_str)
+    }
+
+    pub fn is_folder(&self, guid: &str) -> bool {
+        self.folders.contains(guid)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Path)> {
+        self.guid_to_path
+            .iter()
+            .map(|(guid, path)| (guid.as_str(), path.as_path()))
+    }
+}
+
+/// Whether the given file is a `.meta` file
+pub fn is_meta_file(file: &Path) -> bool {
+    file.extension().is_some_and(|ext| ext == "meta")
+}
+
+/// Returns the path of the asset a `.meta` file belongs to
+pub fn asset_path(meta: &Path) -> PathBuf {
+    meta.with_extension("")
+}
+
+/// Returns the path of the `.meta` file belonging to the given asset
+pub fn meta_path(asset: &Path) -> PathBuf {
+    let mut meta = OsString::from(asset.as_os_str());
+    meta.push(".meta");
+    PathBuf::from(meta)
+}
+
+/// Reads the GUID of an asset from its `.meta` file
+pub async fn read_asset_guid(asset: &Path) -> io::Result<Option<String>> {
+    let content = tokio::fs::read_to_string(meta_path(asset)).await?;
+
+    Ok(parse_meta_guid(&content))
+}
+
+/// Finds the top level `guid` field in the contents of a `.meta` file. This is
+/// done by hand instead of through the YAML parser, as it is always on its own
+/// line near the top of the file.
+pub fn parse_meta_guid(content: &str) -> Option<String> {
+    content
+        .lines()
+        .filter_map(|line| line.strip_prefix("guid:"))
+        .map(str::trim)
+        .find(|guid| is_guid(guid))
+        .map(str::to_owned)
+}
+
+/// Whether the contents of a `.meta` file belong to a folder
+pub fn parse_meta_folder(content: &str) -> bool {
+    content
+        .lines()
+        .any(|line| line.trim_end() == "folderAsset: yes")
+}
+
+/// Whether the given string looks like a Unity GUID (32 hexadecimal digits)
+pub fn is_guid(guid: &str) -> bool {
+    guid.len() == 32 && guid.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Whether the given GUID belongs to one of Unity's built-in assets, such as
+/// the default materials and meshes. Those use GUIDs that start with zeroes
+/// and have no file in the project.
+pub fn is_builtin_guid(guid: &str) -> bool {
+    guid.starts_with("0000000000000000")
+}
@@ -0,0 +1,312 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+const PACKAGES_DIR: &str = "Packages";
+const PACKAGE_CACHE_DIR: &str = "Library/PackageCache";
+const MANIFEST_FILE: &str = "manifest.json";
+const LOCK_FILE: &str = "packages-lock.json";
+const PACKAGE_FILE: &str = "package.json";
+const BUILTIN_MODULE_PREFIX: &str = "com.unity.modules.";
+
+/// Built-in packages with assets that other assets reference, such as the
+/// scripts of UI components. Other built-in packages, like feature sets and
+/// editor tools, are not referenced from the project.
+const BUILTIN_PACKAGES_WITH_ASSETS: &[&str] = &["com.unity.ugui"];
+
+/// A UPM package whose files are indexed next to the project itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Package {
+    /// The name of the package (`com.company.tools`)
+    pub name: String,
+
+    /// The folder containing the `package.json` of the package
+    pub path: PathBuf,
+}
+
+/// The packages of a Unity project
+#[derive(Debug, Clone, Default)]
+pub struct FoundPackages {
+    /// The packages whose files were found
+    pub packages: Vec<Package>,
+
+    /// The names of the packages whose files could not be found. Their assets
+    /// are unknown, so references to them cannot be checked.
+    pub unresolved: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    dependencies: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct LockFile {
+    #[serde(default)]
+    dependencies: BTreeMap<String, LockedPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LockedPackage {
+    version: String,
+    source: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PackageFile {
+    name: String,
+}
+
+/// Finds the packages of the Unity project containing the given folder, using
+/// its `Packages/manifest.json` and `Packages/packages-lock.json`. Embedded
+/// packages, local `file:` packages and packages extracted into
+/// `Library/PackageCache` are returned. Built-in packages that are not in the
+/// cache are left out, which only leaves their assets unknown if they have
+/// any.
+pub fn find_packages(dir: &Path) -> FoundPackages {
+    let Some(project_root) = [Some(dir), dir.parent()]
+        .into_iter()
+        .flatten()
+        .find(|root| root.join(PACKAGES_DIR).join(MANIFEST_FILE).is_file())
+    else {
+        log::info!("No package manifest found, not indexing packages");
+        return FoundPackages::default();
+    };
+
+    let packages_dir = project_root.join(PACKAGES_DIR);
+
+    let manifest: Manifest = read_json(&packages_dir.join(MANIFEST_FILE)).unwrap_or_default();
+    let lock: LockFile = read_json(&packages_dir.join(LOCK_FILE)).unwrap_or_default();
+
+    let mut packages = embedded_packages(&packages_dir);
+    let mut found: HashSet<String> = packages.iter().map(|p| p.name.clone()).collect();
+    let mut unresolved = BTreeSet::new();
+
+    // The lock file also lists indirect dependencies, the manifest is used for
+    // projects that were never opened with a version of Unity writing one
+    let versions = lock
+        .dependencies
+        .into_iter()
+        .map(|(name, locked)| (name, locked.version, locked.source))
+        .chain(
+            manifest
+                .dependencies
+                .into_iter()
+                .map(|(name, version)| (name, version, None)),
+        );
+
+    for (name, version, source) in versions {
+        if found.contains(&name) {
+            continue;
+        }
+
+        // Modules only enable engine features, while other built-in packages
+        // can have assets that are stored with the editor itself
+        if name.starts_with(BUILTIN_MODULE_PREFIX) {
+            continue;
+        }
+
+        let path = match version.strip_prefix("file:") {
+            Some(local) if !local.ends_with(".tgz") => Some(packages_dir.join(local)),
+            _ => cached_package(project_root, &name, &version),
+        };
+
+        // Newer versions of Unity extract built-in packages into the cache as
+        // well, older ones only keep them with the editor
+        if path.is_none() && source.as_deref() == Some("builtin") {
+            if BUILTIN_PACKAGES_WITH_ASSETS.contains(&name.as_str()) {
+                unresolved.insert(name);
+            } else {
+                log::debug!("Skipping built-in package {}", name);
+            }
+
+            continue;
+        }
+
+        let Some(path) = path.and_then(|p| p.canonicalize().ok()) else {
+            log::debug!("Could not find the files of package {}", name);
+            unresolved.insert(name);
+            continue;
+        };
+
+        if !path.join(PACKAGE_FILE).is_file() {
+            log::debug!(
+                "Package {} at {} has no package file",
+                name,
+                path.to_string_lossy()
+            );
+            unresolved.insert(name);
+            continue;
+        }
+
+        log::debug!("Found package {} at {}", name, path.to_string_lossy());
+
+        unresolved.remove(&name);
+        found.insert(name.clone());
+        packages.push(Package { name, path });
+    }
+
+    log::info!("Found {} package(s) to index", packages.len());
+
+    if !unresolved.is_empty() {
+        log::info!(
+            "Could not find the files of {} package(s): {}",
+            unresolved.len(),
+            unresolved.iter().cloned().collect::<Vec<_>>().join(", ")
+        );
+    }
+
+    FoundPackages {
+        packages,
+        unresolved: unresolved.into_iter().collect(),
+    }
+}
+
+/// Returns the package that contains the given file, if any
+pub fn package_for_file<'a>(packages: &'a [Package], file: &Path) -> Option<&'a Package> {
+    packages
+        .iter()
+        .find(|package| file.starts_with(&package.path))
+}
+
+/// Packages stored directly in the `Packages` folder. Their folder name does not
+/// have to match the package name.
+fn embedded_packages(packages_dir: &Path) -> Vec<Package> {
+    let Ok(entries) = fs::read_dir(packages_dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|path| {
+            let package: PackageFile = read_json(&path.join(PACKAGE_FILE))?;
+            let path = path.canonicalize().ok()?;
+
+            log::debug!(
+                "Found embedded package {} at {}",
+                package.name,
+                path.to_string_lossy()
+            );
+
+            Some(Package {
+                name: package.name,
+                path,
+            })
+        })
+        .collect()
+}
+
+/// Registry, git and tarball packages are extracted to `<name>@<version>`, or
+/// `<name>@<hash>` in newer versions of Unity
+fn cached_package(project_root: &Path, name: &str, version: &str) -> Option<PathBuf> {
+    let cache_dir = project_root.join(PACKAGE_CACHE_DIR);
+    let exact = cache_dir.join(format!("{}@{}", name, version));
+
+    if exact.is_dir() {
+        return Some(exact);
+    }
+
+    let prefix = format!("{}@", name);
+
+    fs::read_dir(cache_dir)
+        .ok()?
+        .flatten()
+        .find(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .map(|entry| entry.path())
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(file: &Path) -> Option<T> {
+    let content = fs::read(file).ok()?;
+
+    match serde_json::from_slice(&content) {
+        Ok(parsed) => Some(parsed),
+        Err(e) => {
+            log::warn!("Could not parse {}: {}", file.to_string_lossy(), e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crawler::tests::test_dir;
+
+    const LOCK_FILE_CONTENT: &str = r#"{
+  "dependencies": {
+    "com.company.local": { "version": "file:../Local", "depth": 0, "source": "local", "dependencies": {} },
+    "com.unity.2d.sprite": { "version": "1.0.0", "depth": 0, "source": "builtin", "dependencies": {} },
+    "com.unity.feature.development": { "version": "1.0.2", "depth": 0, "source": "builtin", "dependencies": {} },
+    "com.unity.modules.ui": { "version": "1.0.0", "depth": 0, "source": "builtin", "dependencies": {} },
+    "com.unity.textmeshpro": { "version": "3.0.6", "depth": 0, "source": "registry", "dependencies": {}, "url": "https://packages.unity.com" },
+    "com.unity.timeline": { "version": "1.8.7", "depth": 0, "source": "registry", "dependencies": {}, "url": "https://packages.unity.com" },
+    "com.unity.ugui": { "version": "2.0.0", "depth": 0, "source": "builtin", "dependencies": {} }
+  }
+}"#;
+
+    fn write_package(dir: &Path, name: &str) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(
+            dir.join(PACKAGE_FILE),
+            format!(r#"{{ "name": "{}", "version": "1.0.0" }}"#, name),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn finds_packages_from_the_lock_file() {
+        let project = test_dir("finds-packages");
+        let packages_dir = project.join(PACKAGES_DIR);
+        let cache_dir = project.join(PACKAGE_CACHE_DIR);
+
+        fs::create_dir_all(&packages_dir).unwrap();
+        fs::write(
+            packages_dir.join(MANIFEST_FILE),
+            r#"{ "dependencies": { "com.unity.ugui": "2.0.0" } }"#,
+        )
+        .unwrap();
+        fs::write(packages_dir.join(LOCK_FILE), LOCK_FILE_CONTENT).unwrap();
+
+        write_package(&packages_dir.join("Embedded"), "com.company.embedded");
+        write_package(&project.join("Local"), "com.company.local");
+        write_package(
+            &cache_dir.join("com.unity.timeline@1.8.7"),
+            "com.unity.timeline",
+        );
+        write_package(&cache_dir.join("com.unity.ugui@2f1c9b3a"), "com.unity.ugui");
+
+        let found = find_packages(&project);
+
+        let mut names: Vec<&str> = found.packages.iter().map(|p| p.name.as_str()).collect();
+        names.sort();
+
+        assert_eq!(
+            names,
+            [
+                "com.company.embedded",
+                "com.company.local",
+                "com.unity.timeline",
+                "com.unity.ugui"
+            ]
+        );
+
+        // Built-in packages without assets never make references unverifiable
+        assert_eq!(found.unresolved, ["com.unity.textmeshpro"]);
+
+        // Unless they have assets, but are not in the cache
+        fs::remove_dir_all(cache_dir.join("com.unity.ugui@2f1c9b3a")).unwrap();
+        let found = find_packages(&project);
+
+        assert_eq!(
+            found.unresolved,
+            ["com.unity.textmeshpro", "com.unity.ugui"]
+        );
+
+        _ = fs::remove_dir_all(&project);
+    }
+}
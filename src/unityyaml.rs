@@ -0,0 +1,164 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+use saphyr::{MarkedYaml, ScanError};
+
+static DOCUMENT_HEADER_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^--- !u!(\d+) &(-?\d+)( stripped)?\s*$").unwrap());
+
+/// Unity class ID of `GameObject`
+pub const GAME_OBJECT_CLASS_ID: u32 = 1;
+
+/// Unity class ID of `PrefabInstance`
+pub const PREFAB_INSTANCE_CLASS_ID: u32 = 1001;
+
+/// A single YAML document of a Unity file, each describing a single object
+#[derive(Debug, Clone)]
+pub struct UnityDocument {
+    /// The Unity class ID of the object (`--- !u!<classID> &123`)
+    pub class_id: Option<u32>,
+
+    /// The file-local ID of the object (`--- !u!114 &<fileID>`)
+    pub file_id: Option<i64>,
+
+    /// Whether the document is a stripped placeholder for an object that lives
+    /// in a prefab instance (`--- !u!1 &123 stripped`)
+    pub stripped: bool,
+
+    pub yaml: MarkedYaml,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct DocumentHeader {
+    class_id: Option<u32>,
+    file_id: Option<i64>,
+    stripped: bool,
+}
+
+/// Loads a text-serialized Unity file.
+///
+/// Unity's document headers (`--- !u!<classID> &<fileID> stripped`) are not
+/// valid YAML, and the information in them is not exposed by the YAML parser.
+/// Each header is therefore read separately and replaced by a plain document
+/// start marker before parsing, keeping all lines in place so the positions of
+/// the parsed nodes still match the original file.
+pub fn load_unity_yaml(content: &str) -> Result<Vec<UnityDocument>, ScanError> {
+    let mut headers = Vec::new();
+    let mut cleaned = String::with_capacity(content.len());
+
+    for line in content.lines() {
+        if let Some(header) = parse_header(line) {
+            headers.push(header);
+            cleaned.push_str("---");
+        } else {
+            // Plain document start markers still start a document, other
+            // lines starting with dashes are left to the YAML parser
+            if is_document_start(line) {
+                log::trace!("Non-Unity document header: {}", line);
+                headers.push(DocumentHeader::default());
+            }
+
+            cleaned.push_str(line);
+        }
+
+        cleaned.push('\n');
+    }
+
+    let mut parser = saphyr_parser::Parser::new_from_str(&cleaned);
+    let docs = MarkedYaml::load_from_parser(&mut parser)?;
+
+    // A document without a header is only possible at the very start of a
+    // file, which Unity never writes
+    if docs.len() != headers.len() {
+        log::warn!(
+            "Found {} document headers but {} documents, ignoring headers",
+            headers.len(),
+            docs.len()
+        );
+
+        headers = vec![DocumentHeader::default(); docs.len()];
+    }
+
+    Ok(docs
+        .into_iter()
+        .zip(headers)
+        .map(|(yaml, header)| UnityDocument {
+            class_id: header.class_id,
+            file_id: header.file_id,
+            stripped: header.stripped,
+            yaml,
+        })
+        .collect())
+}
+
+fn parse_header(line: &str) -> Option<DocumentHeader> {
+    let captures = DOCUMENT_HEADER_REGEX.captures(line)?;
+
+    Some(DocumentHeader {
+        class_id: captures[1].parse().ok(),
+        file_id: captures[2].parse().ok(),
+        stripped: captures.get(3).is_some(),
+    })
+}
+
+/// Whether a line is a YAML document start marker (`---`, optionally followed
+/// by content after whitespace)
+fn is_document_start(line: &str) -> bool {
+    line.strip_prefix("---")
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_document_headers() {
+        let docs = load_unity_yaml(
+            "%YAML 1.1\n--- !u!1 &100\nGameObject:\n  m_Name: A\n--- !u!4 &-200 stripped\nTransform:\n  m_PrefabInstance: {fileID: 300}\n",
+        )
+        .unwrap();
+
+        assert_eq!(docs.len(), 2);
+        assert_eq!(docs[0].class_id, Some(1));
+        assert_eq!(docs[0].file_id, Some(100));
+        assert!(!docs[0].stripped);
+        assert_eq!(docs[1].class_id, Some(4));
+        assert_eq!(docs[1].file_id, Some(-200));
+        assert!(docs[1].stripped);
+    }
+
+    #[test]
+    fn keeps_dashes_in_block_scalars() {
+        let docs = load_unity_yaml(
+            "--- !u!114 &1\nMonoBehaviour:\n  m_Text: |\n    ----\n    ---- dashes\n  m_Other: 1\n--- !u!114 &2\nMonoBehaviour:\n  m_Other: 2\n",
+        )
+        .unwrap();
+
+        assert_eq!(docs.len(), 2);
+        assert_eq!(docs[1].file_id, Some(2));
+
+        let text = docs[0].yaml.data.as_hash().unwrap().front().unwrap().1;
+        let text = crate::yamlparser::yaml_get(text, "m_Text").unwrap();
+        assert_eq!(text.data.as_str(), Some("----\n---- dashes\n"));
+    }
+
+    #[test]
+    fn keeps_line_numbers() {
+        let docs = load_unity_yaml("--- !u!1 &1\nGameObject:\n  m_Name: A\n").unwrap();
+
+        assert_eq!(docs[0].yaml.span.start.line(), 2);
+    }
+
+    #[test]
+    fn plain_document_starts_keep_headers_aligned() {
+        let docs = load_unity_yaml(
+            "--- !u!1 &1\nGameObject: {}\n---\nplain: 1\n--- !u!1 &3\nGameObject: {}\n",
+        )
+        .unwrap();
+
+        assert_eq!(docs.len(), 3);
+        assert_eq!(docs[1].file_id, None);
+        assert_eq!(docs[2].file_id, Some(3));
+    }
+}
@@ -0,0 +1,536 @@
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use regex::Regex;
+use saphyr::MarkedYaml;
+use tokio::io::AsyncReadExt;
+
+use crate::crawler::{guids_by_file, Crawler};
+use crate::metafile::{meta_path, AssetTable};
+use crate::unityyaml::load_unity_yaml;
+use crate::yamlparser::{find_object_refs_recursive, yaml_get, yaml_to_guid};
+
+const PROJECT_SETTINGS_DIR: &str = "ProjectSettings";
+const ADDRESSABLE_GROUPS_DIR: &str = "Assets/AddressableAssetsData/AssetGroups";
+
+/// Folders whose contents are loaded by name at runtime, so they are used
+/// without being referenced
+const ROOT_FOLDERS: &[&str] = &["Resources", "StreamingAssets"];
+
+/// Folders whose contents never end up in a build
+const EDITOR_FOLDERS: &[&str] = &["Editor", "Editor Default Resources", "Gizmos"];
+
+/// Files that are used by being compiled or included rather than referenced
+const IGNORED_EXTENSIONS: &[&str] = &[
+    "cs", "asmdef", "asmref", "rsp", "dll", "so", "a", "jar", "aar", "shader", "cginc", "hlsl",
+];
+
+/// Unity's own asset types, which are serialized either as YAML text or in
+/// Unity's binary format, depending on the project settings
+const NATIVE_EXTENSIONS: &[&str] = &[
+    "unity",
+    "prefab",
+    "asset",
+    "controller",
+    "overridecontroller",
+    "anim",
+    "mat",
+    "playable",
+    "spriteatlas",
+    "spriteatlasv2",
+    "mixer",
+    "physicmaterial",
+    "physicsmaterial2d",
+    "terrainlayer",
+    "rendertexture",
+    "customrendertexture",
+    "cubemap",
+    "flare",
+    "fontsettings",
+    "guiskin",
+    "mask",
+    "brush",
+    "lighting",
+    "giparams",
+    "preset",
+    "signal",
+    "shadervariants",
+    "mesh",
+    "vfx",
+];
+
+/// Text formats of Unity packages that reference assets by GUID in JSON or
+/// URLs rather than YAML
+const TEXT_EXTENSIONS: &[&str] = &[
+    "shadergraph",
+    "shadersubgraph",
+    "inputactions",
+    "uxml",
+    "uss",
+];
+
+/// The files Unity bakes lighting into, next to the lighting data of a scene
+const BAKED_LIGHTING_PREFIXES: &[&str] = &["Lightmap-", "ReflectionProbe-"];
+
+/// GUIDs as written in YAML (`guid: x`), JSON (`"guid": "x"`), JSON within a
+/// string (`\"guid\":\"x\"`) and asset URLs (`?guid=x`)
+static GUID_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"guid(?:\\?"?\s*:\s*\\?"?|=)([0-9a-fA-F]{32})"#).unwrap());
+
+#[derive(Debug, Clone)]
+pub struct UnusedAsset {
+    pub guid: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UnusedAssets {
+    /// Assets that cannot be reached
+    pub unused: Vec<UnusedAsset>,
+
+    /// Assets that cannot be reached as far as is known, while some assets
+    /// that can be reached could not be read
+    pub unknown: Vec<UnusedAsset>,
+
+    /// Assets that can be reached, but whose references could not be read
+    /// because they are serialized in Unity's binary format
+    pub unparsed: Vec<UnusedAsset>,
+}
+
+/// What an asset references, as far as it can be found
+enum AssetContents {
+    /// The references in the file are indexed
+    Indexed,
+
+    /// The file is text, but not indexed, so it is searched for GUIDs instead
+    Text(String),
+
+    /// A Unity asset serialized in Unity's binary format
+    Binary,
+
+    /// A binary asset of a type that can only reference the given assets
+    KnownBinary(Vec<String>),
+
+    /// The file cannot reference other assets
+    Leaf,
+}
+
+/// Finds the assets in the `Assets` folder that cannot be reached from the
+/// scenes in the build, the project settings, `Resources` folders or
+/// Addressables groups, sorted by path. As long as any reachable asset could
+/// not be read, the assets that cannot be reached are reported as unknown
+/// rather than unused.
+pub async fn find_unused_assets(crawler: &Crawler) -> UnusedAssets {
+    let project_root = crawler.project_root();
+    let assets_dir = project_root.join("Assets");
+
+    let mut roots = settings_roots(project_root).await;
+    roots.extend(addressable_roots(project_root).await);
+
+    // Assets are read while searching, so the index is not kept locked
+    let assets = crawler.assets.read().await.clone();
+
+    // Addressables can mark an entire folder, which includes everything in it
+    let root_dirs: Vec<&Path> = roots
+        .iter()
+        .filter(|guid| assets.is_folder(guid))
+        .filter_map(|guid| assets.path(guid))
+        .collect();
+
+    let mut new_roots = Vec::new();
+    let mut candidates = Vec::new();
+
+    for (guid, path) in assets.iter() {
+        if in_folder(path, project_root, ROOT_FOLDERS)
+            || root_dirs.iter().any(|dir| path.starts_with(dir))
+        {
+            new_roots.push(guid.to_owned());
+            continue;
+        }
+
+        if !path.starts_with(&assets_dir)
+            || assets.is_folder(guid)
+            || in_folder(path, project_root, EDITOR_FOLDERS)
+        {
+            continue;
+        }
+
+        if !has_extension(path, IGNORED_EXTENSIONS) {
+            candidates.push((guid, path));
+        }
+    }
+
+    roots.extend(new_roots);
+
+    let file_dependencies = guids_by_file(&*crawler.object_refs.read().await);
+
+    let mut reached = HashSet::new();
+    let mut unparsed = Vec::new();
+    let mut queue: VecDeque<String> = roots.into_iter().collect();
+
+    while let Some(guid) = queue.pop_front() {
+        let Some(path) = assets.path(&guid) else {
+            continue;
+        };
+
+        if reached.contains(&guid) {
+            continue;
+        }
+
+        // Importer settings can reference assets as well, such as the
+        // materials remapped by models or the default references of scripts
+        let mut dependencies = meta_dependencies(path, &guid).await;
+
+        match asset_contents(crawler, &assets, &guid, path).await {
+            AssetContents::Indexed => {
+                dependencies.extend(file_dependencies.get(path).into_iter().flatten().cloned());
+            }
+            AssetContents::Text(content) => dependencies.extend(find_guids(&content)),
+            AssetContents::KnownBinary(known) => dependencies.extend(known),
+            AssetContents::Binary => unparsed.push(UnusedAsset {
+                guid: guid.clone(),
+                path: path.to_path_buf(),
+            }),
+            AssetContents::Leaf => (),
+        }
+
+        queue.extend(
+            dependencies
+                .into_iter()
+                .filter(|dependency| !reached.contains(dependency)),
+        );
+
+        reached.insert(guid);
+    }
+
+    let mut unreached: Vec<UnusedAsset> = candidates
+        .into_iter()
+        .filter(|(guid, _)| !reached.contains(*guid))
+        .map(|(guid, path)| UnusedAsset {
+            guid: guid.to_owned(),
+            path: path.to_path_buf(),
+        })
+        .collect();
+
+    unreached.sort_by(|a, b| a.path.cmp(&b.path));
+    unparsed.sort_by(|a, b| a.path.cmp(&b.path));
+
+    if unparsed.is_empty() {
+        UnusedAssets {
+            unused: unreached,
+            ..Default::default()
+        }
+    } else {
+        UnusedAssets {
+            unknown: unreached,
+            unparsed,
+            ..Default::default()
+        }
+    }
+}
+
+/// Folders are matched relative to the project, so the folders the project
+/// itself is in do not count
+fn in_folder(path: &Path, project_root: &Path, folders: &[&str]) -> bool {
+    let relative = path.strip_prefix(project_root).unwrap_or(path);
+
+    relative.parent().is_some_and(|parent| {
+        parent
+            .components()
+            .any(|c| folders.iter().any(|folder| c.as_os_str() == *folder))
+    })
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.contains(&ext.to_lowercase().as_str()))
+}
+
+async fn asset_contents(
+    crawler: &Crawler,
+    assets: &AssetTable,
+    guid: &str,
+    path: &Path,
+) -> AssetContents {
+    if assets.is_folder(guid) || has_extension(path, IGNORED_EXTENSIONS) {
+        return AssetContents::Leaf;
+    }
+
+    if has_extension(path, TEXT_EXTENSIONS) {
+        return match tokio::fs::read_to_string(path).await {
+            Ok(content) => AssetContents::Text(content),
+            Err(e) => {
+                log::debug!("Could not read {}: {}", path.to_string_lossy(), e);
+                AssetContents::Leaf
+            }
+        };
+    }
+
+    let indexed = crawler.is_indexed_file(path);
+
+    if !indexed && !has_extension(path, NATIVE_EXTENSIONS) {
+        return AssetContents::Leaf;
+    }
+
+    // Binary files are skipped when indexing, so the header is checked first
+    if !is_yaml_file(path).await {
+        return match binary_dependencies(assets, path) {
+            Some(known) => AssetContents::KnownBinary(known),
+            None => AssetContents::Binary,
+        };
+    }
+
+    if indexed {
+        return AssetContents::Indexed;
+    }
+
+    match tokio::fs::read_to_string(path).await {
+        Ok(content) => AssetContents::Text(content),
+        Err(e) => {
+            log::debug!("Could not read {}: {}", path.to_string_lossy(), e);
+            AssetContents::Binary
+        }
+    }
+}
+
+/// The assets a binary asset can reference, for the types Unity always
+/// serializes in binary. Baked lighting data references the lightmaps and
+/// reflection probes baked next to it, and navmesh data references nothing.
+fn binary_dependencies(assets: &AssetTable, path: &Path) -> Option<Vec<String>> {
+    let name = path.file_name()?.to_str()?;
+
+    if name.starts_with("NavMesh") && has_extension(path, &["asset"]) {
+        return Some(Vec::new());
+    }
+
+    if name != "LightingData.asset" {
+        return None;
+    }
+
+    let baked = assets
+        .iter()
+        .filter(|(_, asset)| asset.parent() == path.parent())
+        .filter(|(_, asset)| {
+            asset
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    BAKED_LIGHTING_PREFIXES
+                        .iter()
+                        .any(|prefix| name.starts_with(prefix))
+                })
+        })
+        .map(|(guid, _)| guid.to_owned())
+        .collect();
+
+    Some(baked)
+}
+
+async fn is_yaml_file(path: &Path) -> bool {
+    let mut header = [0; 5];
+
+    match tokio::fs::File::open(path).await {
+        Ok(mut file) => file
+            .read_exact(&mut header)
+            .await
+            .is_ok_and(|_| &header == b"%YAML"),
+        Err(_) => false,
+    }
+}
+
+/// The GUIDs referenced by the `.meta` file of an asset, other than its own
+async fn meta_dependencies(path: &Path, guid: &str) -> HashSet<String> {
+    match tokio::fs::read_to_string(meta_path(path)).await {
+        Ok(content) => find_guids(&content)
+            .filter(|dependency| dependency != guid)
+            .collect(),
+        Err(e) => {
+            log::debug!(
+                "Could not read the meta file of {}: {}",
+                path.to_string_lossy(),
+                e
+            );
+            HashSet::new()
+        }
+    }
+}
+
+fn find_guids(content: &str) -> impl Iterator<Item = String> + '_ {
+    GUID_REGEX
+        .captures_iter(content)
+        .map(|captures| captures[1].to_lowercase())
+}
+
+/// Everything referenced by the project settings, which includes the scenes
+/// in the build
+async fn settings_roots(project_root: &Path) -> HashSet<String> {
+    let mut roots = HashSet::new();
+
+    for doc in read_yaml_dir(&project_root.join(PROJECT_SETTINGS_DIR)).await {
+        let mut found = Vec::new();
+        find_object_refs_recursive(&doc, "", &mut found);
+        roots.extend(found.into_iter().map(|(object, _, _)| object.guid));
+
+        // Scenes are listed by their GUID alone
+        let scenes = yaml_get(&doc, "EditorBuildSettings")
+            .and_then(|settings| yaml_get(settings, "m_Scenes"))
+            .and_then(|scenes| scenes.data.as_vec());
+
+        for scene in scenes.into_iter().flatten() {
+            let enabled = yaml_get(scene, "enabled")
+                .and_then(|enabled| enabled.data.as_i64())
+                .is_none_or(|enabled| enabled != 0);
+
+            if let Some(guid) = yaml_get(scene, "guid").and_then(yaml_to_guid) {
+                if enabled {
+                    roots.insert(guid);
+                }
+            }
+        }
+    }
+
+    roots
+}
+
+/// The assets and folders added to any Addressables group
+async fn addressable_roots(project_root: &Path) -> HashSet<String> {
+    let mut roots = HashSet::new();
+
+    for doc in read_yaml_dir(&project_root.join(ADDRESSABLE_GROUPS_DIR)).await {
+        let entries = yaml_get(&doc, "MonoBehaviour")
+            .and_then(|group| yaml_get(group, "m_SerializeEntries"))
+            .and_then(|entries| entries.data.as_vec());
+
+        roots.extend(
+            entries
+                .into_iter()
+                .flatten()
+                .filter_map(|entry| yaml_get(entry, "m_GUID").and_then(yaml_to_guid)),
+        );
+    }
+
+    roots
+}
+
+/// Reads all documents of the `.asset` files in a folder, skipping files that
+/// are not text-serialized
+async fn read_yaml_dir(dir: &Path) -> Vec<MarkedYaml> {
+    let mut docs = Vec::new();
+
+    let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+        log::debug!("Could not read {}", dir.to_string_lossy());
+        return docs;
+    };
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+
+        if path.extension().is_none_or(|ext| ext != "asset") {
+            continue;
+        }
+
+        let Ok(content) = tokio::fs::read_to_string(&path).await else {
+            log::debug!("Skipping binary settings file {}", path.to_string_lossy());
+            continue;
+        };
+
+        match load_unity_yaml(&content) {
+            Ok(file_docs) => docs.extend(file_docs.into_iter().map(|doc| doc.yaml)),
+            Err(e) => log::warn!("Could not parse {}: {}", path.to_string_lossy(), e),
+        }
+    }
+
+    docs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_guids_in_yaml_json_and_urls() {
+        let content = concat!(
+            "  m_Texture: {fileID: 2800000, guid: 0123456789abcdef0123456789abcdef, type: 3}\n",
+            "\"m_SerializedTexture\": \"{\\\"texture\\\":{\\\"fileID\\\":2800000,\\\"guid\\\":\\\"11111111111111111111111111111111\\\",\\\"type\\\":3}}\"\n",
+            "  \"guid\": \"22222222222222222222222222222222\"\n",
+            "<ui:Style src=\"project://database/Assets/Menu.uss?fileID=7433441132597879392&amp;guid=33333333333333333333333333333333&amp;type=3\" />\n",
+            "  guid: not-a-guid\n",
+        );
+
+        let guids: Vec<String> = find_guids(content).collect();
+
+        assert_eq!(
+            guids,
+            [
+                "0123456789abcdef0123456789abcdef",
+                "11111111111111111111111111111111",
+                "22222222222222222222222222222222",
+                "33333333333333333333333333333333",
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_folders_within_the_project() {
+        let root = Path::new("/Users/dev/Resources/Game");
+
+        assert!(!in_folder(
+            Path::new("/Users/dev/Resources/Game/Assets/Art/Dead.png"),
+            root,
+            ROOT_FOLDERS
+        ));
+        assert!(in_folder(
+            Path::new("/Users/dev/Resources/Game/Assets/Art/Resources/Icon.png"),
+            root,
+            ROOT_FOLDERS
+        ));
+        assert!(in_folder(
+            Path::new("/Users/dev/Resources/Game/Assets/Editor/Tool.png"),
+            root,
+            EDITOR_FOLDERS
+        ));
+        assert!(!in_folder(
+            Path::new("/Users/dev/Resources/Game/Assets/Editor.png"),
+            root,
+            EDITOR_FOLDERS
+        ));
+    }
+
+    #[test]
+    fn knows_what_binary_lighting_and_navmesh_data_references() {
+        let scene_dir = Path::new("/project/Assets/Level");
+        let mut assets = AssetTable::default();
+
+        for (guid, name) in [
+            ("a", "LightingData.asset"),
+            ("b", "Lightmap-0_comp_light.exr"),
+            ("c", "ReflectionProbe-0.exr"),
+            ("d", "Floor.mat"),
+            ("e", "NavMesh.asset"),
+        ] {
+            assets.insert(guid.to_owned(), scene_dir.join(name), false);
+        }
+
+        assets.insert(
+            "f".to_owned(),
+            PathBuf::from("/project/Assets/Other/Lightmap-0_comp_light.exr"),
+            false,
+        );
+
+        let mut baked =
+            binary_dependencies(&assets, &scene_dir.join("LightingData.asset")).unwrap();
+        baked.sort();
+
+        assert_eq!(baked, ["b", "c"]);
+        assert_eq!(
+            binary_dependencies(&assets, &scene_dir.join("NavMesh.asset")),
+            Some(Vec::new())
+        );
+        assert_eq!(
+            binary_dependencies(&assets, &scene_dir.join("Terrain.asset")),
+            None
+        );
+    }
+}
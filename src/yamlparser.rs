@@ -1,66 +1,208 @@
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 
-use saphyr::Yaml;
+use saphyr::{AnnotatedHash, MarkedYaml, YamlData};
 
-use crate::crawler::{ArcRefSet, MethodDefinition, Reference};
+use crate::crawler::{
+    ArcRefSet, CallState, ListenerMode, MethodDefinition, ObjectDefinition, ObjectPointer,
+    Reference, ReferenceKind, SourceCall, StaticArgument, UnityEventCall,
+};
+use crate::hierarchy::FileHierarchy;
+use crate::metafile::is_guid;
+use crate::packages::package_for_file;
+use crate::unityyaml::UnityDocument;
 
-pub async fn search_yaml_doc(doc: &Yaml, refs: &ArcRefSet, origin_file: &Path) {
-    if !matches!(doc, Yaml::Hash(_)) {
+pub async fn search_yaml_doc(
+    doc: &UnityDocument,
+    refs: &ArcRefSet,
+    origin_file: &Path,
+    hierarchy: &FileHierarchy,
+) {
+    if !matches!(doc.yaml.data, YamlData::Hash(_)) {
         log::warn!("Unknown Unity YAML root document type");
         return;
     }
 
-    let as_mono = &doc["MonoBehaviour"];
-    if !matches!(as_mono, Yaml::BadValue) {
-        search_monobehaviour(as_mono, refs, origin_file).await;
+    // Stripped documents only link an object to its counterpart in the source
+    // prefab, which is already referenced by the prefab instance itself
+    if doc.stripped {
+        log::trace!("Skipping stripped object {:?}", doc.file_id);
+        return;
+    }
+
+    let my_ref = Reference {
+        file: origin_file.to_path_buf(),
+        line: None,
+        asset: origin_file
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string()),
+        object: doc.file_id.and_then(|id| hierarchy.object_path(id)),
+        package: package_for_file(&refs.packages, origin_file).map(|p| p.name.clone()),
+        kind: ReferenceKind::Object { property: None },
+    };
+
+    if let Some(as_mono) = yaml_get(&doc.yaml, "MonoBehaviour") {
+        search_monobehaviour(as_mono, refs, &my_ref, hierarchy).await;
+    }
+
+    if let Some(as_instance) = yaml_get(&doc.yaml, "PrefabInstance") {
+        search_prefab_instance(as_instance, refs, &my_ref, hierarchy).await;
+    }
+
+    if let Some(as_clip) = yaml_get(&doc.yaml, "AnimationClip") {
+        search_animation_clip(as_clip, refs, &my_ref).await;
+    }
+
+    search_object_refs(&doc.yaml, refs, &my_ref).await;
+}
+
+async fn search_object_refs(doc: &MarkedYaml, refs: &ArcRefSet, my_ref: &Reference) {
+    let mut found_objects = Vec::new();
+
+    // Properties are named relative to the object, without its class name
+    for object in doc
+        .data
+        .as_hash()
+        .into_iter()
+        .flat_map(|hash| hash.values())
+    {
+        find_object_refs_recursive(object, "", &mut found_objects);
+    }
+
+    if found_objects.is_empty() {
+        return;
+    }
+
+    let mut refs_locked = refs.objects.write().await;
+
+    for (found_object, line, property) in found_objects {
+        refs_locked
+            .entry(found_object)
+            .or_default()
+            .push(Reference {
+                line: Some(line),
+                kind: ReferenceKind::Object {
+                    property: Some(property),
+                },
+                ..my_ref.clone()
+            });
+    }
+}
+
+/// Finds the object references below a node, along with the property holding
+/// each of them. Properties are written the way Unity writes property paths
+/// (`m_Materials.Array.data[0]`), starting from the given path of the node.
+pub fn find_object_refs_recursive(
+    node: &MarkedYaml,
+    property: &str,
+    found: &mut Vec<(ObjectDefinition, usize, String)>,
+) {
+    match &node.data {
+        YamlData::Array(yamls) => {
+            for (i, yaml) in yamls.iter().enumerate() {
+                find_object_refs_recursive(yaml, &format!("{}.Array.data[{}]", property, i), found);
+            }
+        }
+        YamlData::Hash(linked_hash_map) => {
+            // Object references are always written as `{fileID: x, guid: y, type: z}`
+            if yaml_get(node, "fileID").is_some() {
+                if let Some(guid) = yaml_get(node, "guid").and_then(yaml_to_guid) {
+                    log::trace!("Found reference to object {} in {}", guid, property);
+                    found.push((
+                        ObjectDefinition { guid },
+                        node.span.start.line(),
+                        property.to_owned(),
+                    ));
+                }
+
+                return;
+            }
+
+            // Prefab modifications name the property they override separately
+            let modified = yaml_get(node, "propertyPath").and_then(yaml_to_string);
+
+            for (key, val) in linked_hash_map {
+                let key = yaml_to_string(key).unwrap_or_default();
+
+                let child = match &modified {
+                    Some(path) if key == "objectReference" => path.clone(),
+                    _ if property.is_empty() => key,
+                    _ => format!("{}.{}", property, key),
+                };
+
+                find_object_refs_recursive(val, &child, found);
+            }
+        }
+        _ => (),
     }
 }
 
-async fn search_monobehaviour(mono: &Yaml, refs: &ArcRefSet, origin_file: &Path) {
+/// GUIDs are plain scalars, so depending on their contents the YAML parser can
+/// turn them into numbers
+pub fn yaml_to_guid(node: &MarkedYaml) -> Option<String> {
+    let guid = match &node.data {
+        YamlData::String(s) | YamlData::Real(s) => s.clone(),
+        YamlData::Integer(i) if *i >= 0 => format!("{:032}", i),
+        _ => return None,
+    };
+
+    is_guid(&guid).then_some(guid)
+}
+
+async fn search_monobehaviour(
+    mono: &MarkedYaml,
+    refs: &ArcRefSet,
+    my_ref: &Reference,
+    hierarchy: &FileHierarchy,
+) {
     assert!(
-        matches!(mono, Yaml::Hash(_)),
+        matches!(mono.data, YamlData::Hash(_)),
         "MonoBehaviour YAML node can only be a hashmap"
     );
 
-    let my_method_ref = Reference {
-        file: origin_file.to_path_buf(),
-        line: None,
-        asset: None,
-        object: None,
-    };
+    if let Some(script) = yaml_get(mono, "m_Script") {
+        if let Some(guid) = yaml_get(script, "guid").and_then(yaml_to_guid) {
+            refs.components
+                .write()
+                .await
+                .entry(ObjectDefinition { guid })
+                .or_default()
+                .push(Reference {
+                    line: Some(script.span.start.line()),
+                    ..my_ref.clone()
+                });
+        }
+    }
 
-    search_mono_fields_recursive(mono, refs, &my_method_ref).await;
+    search_mono_fields_recursive(mono, refs, my_ref, hierarchy).await;
 }
 
-async fn search_mono_fields_recursive(node: &Yaml, refs: &ArcRefSet, my_ref: &Reference) {
+async fn search_mono_fields_recursive(
+    node: &MarkedYaml,
+    refs: &ArcRefSet,
+    my_ref: &Reference,
+    hierarchy: &FileHierarchy,
+) {
     log::trace!("Searching YAML node");
 
-    match node {
-        Yaml::Array(yamls) => {
+    match &node.data {
+        YamlData::Array(yamls) => {
             let futures: Vec<_> = yamls
                 .iter()
-                .map(|yaml| search_mono_fields_recursive(yaml, refs, my_ref))
+                .map(|yaml| search_mono_fields_recursive(yaml, refs, my_ref, hierarchy))
                 .collect();
 
             futures::future::join_all(futures).await;
         }
-        Yaml::Hash(linked_hash_map) => {
+        YamlData::Hash(linked_hash_map) => {
             let futures: Vec<_> = linked_hash_map
                 .iter()
                 .map(|(key, val)| async move {
-                    if key == &Yaml::String(String::from("m_PersistentCalls")) {
-                        let found_method_calls = parse_persistent_calls(val);
-
-                        let mut refs_locked = refs.methods.write().await;
-
-                        for found_method_call in found_method_calls {
-                            refs_locked
-                                .entry(found_method_call)
-                                .or_default()
-                                .push(my_ref.clone());
-                        }
+                    if key.data.as_str() == Some("m_PersistentCalls") {
+                        let found_calls = parse_persistent_calls(val, hierarchy);
+                        add_calls(found_calls, refs, my_ref, hierarchy).await;
                     } else {
-                        search_mono_fields_recursive(val, refs, my_ref).await;
+                        search_mono_fields_recursive(val, refs, my_ref, hierarchy).await;
                     }
                 })
                 .collect();
@@ -71,35 +213,900 @@ async fn search_mono_fields_recursive(node: &Yaml, refs: &ArcRefSet, my_ref: &Re
     }
 }
 
-fn parse_persistent_calls(persistent_calls: &Yaml) -> Vec<MethodDefinition> {
+/// Resolves the types of the given calls and adds them as method references
+async fn add_calls(
+    found_calls: Vec<FoundCall>,
+    refs: &ArcRefSet,
+    my_ref: &Reference,
+    hierarchy: &FileHierarchy,
+) {
+    if found_calls.is_empty() {
+        return;
+    }
+
+    let mut found_method_calls = Vec::new();
+
+    for found_call in found_calls {
+        found_method_calls.push(resolve_call(found_call, refs, hierarchy).await);
+    }
+
+    let mut refs_locked = refs.methods.write().await;
+
+    for (found_method_call, line, details) in found_method_calls {
+        refs_locked
+            .entry(found_method_call)
+            .or_default()
+            .push(Reference {
+                line: Some(line),
+                kind: ReferenceKind::UnityEvent(Box::new(details)),
+                ..my_ref.clone()
+            });
+    }
+}
+
+/// Prefab instances and variants store the UnityEvent listeners they add or
+/// change as modifications of single properties of the source prefab
+/// (`m_OnClick.m_PersistentCalls.m_Calls.Array.data[0].m_MethodName`). Those
+/// are put back together into calls, attributed to the instance. Listeners
+/// whose method is not overridden keep the reference from the source prefab.
+/// The source prefab itself is recorded as well, to find where prefabs are
+/// instantiated.
+async fn search_prefab_instance(
+    instance: &MarkedYaml,
+    refs: &ArcRefSet,
+    my_ref: &Reference,
+    hierarchy: &FileHierarchy,
+) {
+    if let Some(source) = yaml_get(instance, "m_SourcePrefab") {
+        if let Some(guid) = yaml_get(source, "guid").and_then(yaml_to_guid) {
+            refs.instances
+                .write()
+                .await
+                .entry(ObjectDefinition { guid })
+                .or_default()
+                .push(Reference {
+                    line: Some(source.span.start.line()),
+                    ..my_ref.clone()
+                });
+        }
+    }
+
+    let Some(YamlData::Array(modifications)) = yaml_get(instance, "m_Modification")
+        .and_then(|m| yaml_get(m, "m_Modifications"))
+        .map(|m| &m.data)
+    else {
+        return;
+    };
+
+    let mut found_calls = Vec::new();
+
+    for (source, call) in collect_modified_calls(modifications) {
+        // Calls that only override some of their fields, such as the method
+        // name, take the others from the call they override. Fields that
+        // cannot be found there are left out.
+        let partial = !is_complete_call(&call);
+
+        let call = match &source {
+            Some(source) if partial => match refs.source_call(source).await {
+                Some(mut source_call) => {
+                    overlay_fields(&mut source_call, &call);
+                    source_call.span = call.span;
+                    source_call
+                }
+                None => call,
+            },
+            _ => call,
+        };
+
+        let Some(mut found) = parse_call(&call, hierarchy) else {
+            continue;
+        };
+
+        if partial {
+            found.details.source = source;
+        }
+
+        found_calls.push(found);
+    }
+
+    add_calls(found_calls, refs, my_ref, hierarchy).await;
+}
+
+const PERSISTENT_CALL_PATH: &str = "m_PersistentCalls.m_Calls.Array.data[";
+
+/// Rebuilds the YAML of every persistent call that has modified fields, along
+/// with the call in the source prefab it overrides. The rebuilt call starts at
+/// the line of its method name, if that is modified.
+fn collect_modified_calls(modifications: &[MarkedYaml]) -> Vec<(Option<SourceCall>, MarkedYaml)> {
+    let mut calls: BTreeMap<(Option<i64>, Option<String>, String), MarkedYaml> = BTreeMap::new();
+
+    for modification in modifications {
+        let Some(property_path) =
+            yaml_get(modification, "propertyPath").and_then(|p| p.data.as_str())
+        else {
+            continue;
+        };
+
+        let Some((call_path, field)) = split_call_property(property_path) else {
+            continue;
+        };
+
+        // Object fields are overridden through the object reference, the
+        // value is left empty
+        let value = match field {
+            "m_Target" | "m_Arguments.m_ObjectArgument" => {
+                yaml_get(modification, "objectReference")
+            }
+            _ => yaml_get(modification, "value"),
+        };
+
+        let Some(value) = value else {
+            continue;
+        };
+
+        let target = yaml_get(modification, "target");
+        let key = (
+            target.and_then(yaml_file_id),
+            target
+                .and_then(|t| yaml_get(t, "guid"))
+                .and_then(yaml_to_guid),
+            call_path.to_owned(),
+        );
+
+        let call = calls.entry(key).or_insert_with(|| MarkedYaml {
+            span: modification.span,
+            data: YamlData::Hash(AnnotatedHash::new()),
+        });
+
+        if field == "m_MethodName" {
+            call.span = modification.span;
+        }
+
+        insert_field(call, field, value.clone());
+    }
+
+    calls
+        .into_iter()
+        .map(|((file_id, prefab, property), call)| {
+            let source = file_id.zip(prefab).map(|(file_id, prefab)| SourceCall {
+                prefab,
+                file_id,
+                property,
+            });
+
+            (source, call)
+        })
+        .collect()
+}
+
+/// Every field of a persistent call, as property paths within the call
+const CALL_FIELDS: &[&str] = &[
+    "m_Target",
+    "m_TargetAssemblyTypeName",
+    "m_MethodName",
+    "m_Mode",
+    "m_Arguments.m_ObjectArgument",
+    "m_Arguments.m_ObjectArgumentAssemblyTypeName",
+    "m_Arguments.m_IntArgument",
+    "m_Arguments.m_FloatArgument",
+    "m_Arguments.m_StringArgument",
+    "m_Arguments.m_BoolArgument",
+    "m_CallState",
+];
+
+/// Whether a call rebuilt from modifications sets every field itself, as for
+/// listeners added in the prefab instance
+fn is_complete_call(call: &MarkedYaml) -> bool {
+    CALL_FIELDS
+        .iter()
+        .all(|field| yaml_get_property(call, field).is_some())
+}
+
+/// Replaces the fields of a call with the ones set in the overriding call
+fn overlay_fields(call: &mut MarkedYaml, overrides: &MarkedYaml) {
+    let Some(fields) = overrides.data.as_hash() else {
+        return;
+    };
+
+    for (key, value) in fields {
+        let Some(key) = key.data.as_str() else {
+            continue;
+        };
+
+        match yaml_get_mut(call, key) {
+            Some(existing)
+                if existing.data.as_hash().is_some() && value.data.as_hash().is_some() =>
+            {
+                overlay_fields(existing, value);
+            }
+            _ => insert_field(call, key, value.clone()),
+        }
+    }
+}
+
+fn yaml_get_mut<'a>(node: &'a mut MarkedYaml, key: &str) -> Option<&'a mut MarkedYaml> {
+    match &mut node.data {
+        YamlData::Hash(hash) => hash.get_mut(&MarkedYaml::from(YamlData::String(key.to_owned()))),
+        _ => None,
+    }
+}
+
+fn find_source_call<'a>(source: &SourceCall, docs: &'a [UnityDocument]) -> Option<&'a MarkedYaml> {
+    let object = docs
+        .iter()
+        .find(|doc| doc.file_id == Some(source.file_id))?;
+    let (_, object) = object.yaml.data.as_hash()?.front()?;
+
+    yaml_get_property(object, &source.property)
+}
+
+/// Finds the call a modified call of a prefab instance overrides in the
+/// documents of the source prefab. Its references to objects of the source
+/// prefab get the GUID of the prefab, as they are read from another file.
+pub fn source_call_yaml(source: &SourceCall, docs: &[UnityDocument]) -> Option<MarkedYaml> {
+    let mut call = find_source_call(source, docs)?.clone();
+
+    for field in ["m_Target", "m_Arguments.m_ObjectArgument"] {
+        let local = yaml_get_property(&call, field)
+            .filter(|object| yaml_file_id(object).is_some_and(|id| id != 0))
+            .is_some_and(|object| yaml_get(object, "guid").is_none());
+
+        if local {
+            insert_field(
+                &mut call,
+                &format!("{}.guid", field),
+                MarkedYaml::from(YamlData::String(source.prefab.clone())),
+            );
+        }
+    }
+
+    Some(call)
+}
+
+/// Finds the call a modified call of a prefab instance overrides in the
+/// documents of the source prefab, and looks up its class and assembly
+pub async fn resolve_source_call(
+    source: &SourceCall,
+    docs: &[UnityDocument],
+    refs: &ArcRefSet,
+) -> Option<(String, String)> {
+    let call = find_source_call(source, docs)?;
+    let hierarchy = FileHierarchy::from_documents(docs, &HashMap::new());
+    let found = parse_call(call, &hierarchy)?;
+
+    if let (Some(typename), Some(assembly)) = (&found.typename, &found.assembly) {
+        return Some((typename.clone(), assembly.clone()));
+    }
+
+    let script = found
+        .details
+        .target
+        .as_ref()
+        .filter(|target| target.guid.is_none())
+        .and_then(|target| hierarchy.script(target.file_id))?;
+
+    let (class, assembly) = refs.script_type(script).await?;
+
+    Some((found.typename.unwrap_or(class), assembly))
+}
+
+/// Gets the node at a property path as Unity writes it
+/// (`m_Calls.Array.data[0].m_Target`)
+fn yaml_get_property<'a>(node: &'a MarkedYaml, property: &str) -> Option<&'a MarkedYaml> {
+    property
+        .split('.')
+        .filter(|part| *part != "Array")
+        .try_fold(node, |node, part| {
+            match part
+                .strip_prefix("data[")
+                .and_then(|index| index.strip_suffix(']'))
+            {
+                Some(index) => node.data.as_vec()?.get(index.parse::<usize>().ok()?),
+                None => yaml_get(node, part),
+            }
+        })
+}
+
+/// Splits a property path into the path of the persistent call and the field
+/// of the call (`m_Arguments.m_IntArgument`)
+fn split_call_property(property_path: &str) -> Option<(&str, &str)> {
+    let index_start = property_path.find(PERSISTENT_CALL_PATH)? + PERSISTENT_CALL_PATH.len();
+    let call_end = index_start + property_path[index_start..].find(']')? + 1;
+
+    Some((
+        &property_path[..call_end],
+        property_path[call_end..].strip_prefix('.')?,
+    ))
+}
+
+/// Inserts a value at a dotted path into a YAML hash node, creating the hashes
+/// in between
+fn insert_field(node: &mut MarkedYaml, field: &str, value: MarkedYaml) {
+    let YamlData::Hash(hash) = &mut node.data else {
+        return;
+    };
+
+    match field.split_once('.') {
+        Some((key, rest)) => {
+            let child = hash
+                .entry(MarkedYaml::from(YamlData::String(key.to_owned())))
+                .or_insert_with(|| MarkedYaml::from(YamlData::Hash(AnnotatedHash::new())));
+
+            insert_field(child, rest, value);
+        }
+        None => {
+            hash.insert(MarkedYaml::from(YamlData::String(field.to_owned())), value);
+        }
+    }
+}
+
+async fn search_animation_clip(clip: &MarkedYaml, refs: &ArcRefSet, my_ref: &Reference) {
+    let Some(YamlData::Array(events)) = yaml_get(clip, "m_Events").map(|e| &e.data) else {
+        return;
+    };
+
+    if events.is_empty() {
+        return;
+    }
+
+    let clip_name = yaml_get(clip, "m_Name")
+        .and_then(yaml_to_string)
+        .unwrap_or_default();
+
+    let mut refs_locked = refs.methods.write().await;
+
+    for event in events {
+        // Placeholder events are written without a function name
+        let Some(function_name) = yaml_get(event, "functionName")
+            .and_then(yaml_to_string)
+            .filter(|name| !name.is_empty())
+        else {
+            continue;
+        };
+
+        let time = yaml_get(event, "time")
+            .and_then(yaml_to_f64)
+            .unwrap_or_default();
+
+        log::trace!(
+            "Found animation event calling {} at {}s in clip {}",
+            function_name,
+            time,
+            clip_name
+        );
+
+        refs_locked
+            .entry(MethodDefinition::untyped(function_name))
+            .or_default()
+            .push(Reference {
+                line: Some(event.span.start.line()),
+                kind: ReferenceKind::AnimationEvent {
+                    clip: clip_name.clone(),
+                    time,
+                },
+                ..my_ref.clone()
+            });
+    }
+}
+
+/// A persistent call as written in the file, before the type it is called on
+/// is resolved
+struct FoundCall {
+    method_name: String,
+    typename: Option<String>,
+    assembly: Option<String>,
+    line: usize,
+    details: UnityEventCall,
+}
+
+fn parse_persistent_calls(
+    persistent_calls: &MarkedYaml,
+    hierarchy: &FileHierarchy,
+) -> Vec<FoundCall> {
     log::trace!("Found persistent call: {:#?}", persistent_calls);
 
-    if let Yaml::Array(targets) = &persistent_calls["m_Calls"] {
-        targets.iter().filter_map(parse_call).collect()
+    if let Some(YamlData::Array(targets)) = yaml_get(persistent_calls, "m_Calls").map(|c| &c.data) {
+        targets
+            .iter()
+            .filter_map(|call| parse_call(call, hierarchy))
+            .collect()
     } else {
         Vec::new()
     }
 }
 
-fn parse_call(call: &Yaml) -> Option<MethodDefinition> {
-    let method_target = &call["m_MethodName"];
-    let target_assembly_type = &call["m_TargetAssemblyTypeName"];
+fn parse_call(call: &MarkedYaml, hierarchy: &FileHierarchy) -> Option<FoundCall> {
+    let method_name = yaml_get(call, "m_MethodName").and_then(|m| m.data.as_str())?;
+
+    // Older versions of Unity write the full assembly qualified name
+    // (`Game.Menu, Assembly-CSharp, Version=0.0.0.0, Culture=neutral, PublicKeyToken=null`)
+    // or leave it out entirely
+    let target_assembly_type = yaml_get(call, "m_TargetAssemblyTypeName")
+        .and_then(|t| t.data.as_str())
+        .unwrap_or_default();
+
+    let mut type_parts = target_assembly_type
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(str::to_owned);
+
+    Some(FoundCall {
+        method_name: method_name.to_owned(),
+        typename: type_parts.next(),
+        assembly: type_parts.next(),
+        line: call.span.start.line(),
+        details: parse_call_details(call, hierarchy),
+    })
+}
+
+/// Completes the type of a call that does not store it, by looking up the
+/// script of the MonoBehaviour it targets. Calls whose target cannot be
+/// resolved are recorded with an unknown target.
+async fn resolve_call(
+    mut found: FoundCall,
+    refs: &ArcRefSet,
+    hierarchy: &FileHierarchy,
+) -> (MethodDefinition, usize, UnityEventCall) {
+    if found.typename.is_none() || found.assembly.is_none() {
+        found.details.script = found
+            .details
+            .target
+            .as_ref()
+            .filter(|target| target.guid.is_none())
+            .and_then(|target| hierarchy.script(target.file_id))
+            .map(str::to_owned);
+
+        if let Some(script) = &found.details.script {
+            if let Some((class, assembly)) = refs.script_type(script).await {
+                found.typename.get_or_insert(class);
+                found.assembly.get_or_insert(assembly);
+            }
+        }
+    }
+
+    let found_method_call = match found.typename {
+        Some(typename) => MethodDefinition {
+            method_name: found.method_name,
+            method_assembly: found.assembly.unwrap_or_default(),
+            method_typename: typename,
+        },
+        None => {
+            log::debug!(
+                "Could not resolve the target of a call to {}",
+                found.method_name
+            );
+
+            MethodDefinition::unknown_target(found.method_name)
+        }
+    };
+
+    log::trace!("Found call to {:#?}", found_method_call);
+
+    (found_method_call, found.line, found.details)
+}
+
+/// Reads the target, argument and state of a persistent call. Values that are
+/// missing or unknown are left out.
+fn parse_call_details(call: &MarkedYaml, hierarchy: &FileHierarchy) -> UnityEventCall {
+    let mode = yaml_get(call, "m_Mode")
+        .and_then(|m| m.data.as_i64())
+        .and_then(ListenerMode::from_serialized);
 
-    if let Yaml::String(method_name) = method_target {
-        if let Yaml::String(method_assembly_type) = target_assembly_type {
-            let (class, assembly) = method_assembly_type.split_once(", ").expect("REMOVE THIS");
+    let call_state = yaml_get(call, "m_CallState")
+        .and_then(|s| s.data.as_i64())
+        .and_then(CallState::from_serialized);
 
-            let found_method_call = MethodDefinition {
-                method_name: method_name.clone(),
-                method_assembly: assembly.to_owned(),
-                method_typename: class.to_owned(),
-            };
+    let argument = mode
+        .zip(yaml_get(call, "m_Arguments"))
+        .and_then(|(mode, arguments)| parse_static_argument(arguments, mode, hierarchy));
 
-            log::trace!("Found call to {:#?}", found_method_call);
+    UnityEventCall {
+        target: yaml_get(call, "m_Target").and_then(|t| parse_object_pointer(t, hierarchy)),
+        mode,
+        argument,
+        call_state,
+        script: None,
+        source: None,
+    }
+}
 
-            return Some(found_method_call);
+fn parse_static_argument(
+    arguments: &MarkedYaml,
+    mode: ListenerMode,
+    hierarchy: &FileHierarchy,
+) -> Option<StaticArgument> {
+    let argument = match mode {
+        ListenerMode::Dynamic | ListenerMode::Void => return None,
+        ListenerMode::Object => StaticArgument::Object {
+            object: yaml_get(arguments, "m_ObjectArgument")
+                .and_then(|o| parse_object_pointer(o, hierarchy)),
+            type_name: yaml_get(arguments, "m_ObjectArgumentAssemblyTypeName")
+                .and_then(yaml_to_string),
+        },
+        ListenerMode::Int => {
+            StaticArgument::Int(yaml_get(arguments, "m_IntArgument")?.data.as_i64()?)
+        }
+        ListenerMode::Float => {
+            StaticArgument::Float(yaml_to_f64(yaml_get(arguments, "m_FloatArgument")?)?)
         }
+        // Empty strings are written without a value, which parses as null
+        ListenerMode::String => StaticArgument::String(
+            yaml_to_string(yaml_get(arguments, "m_StringArgument")?).unwrap_or_default(),
+        ),
+        ListenerMode::Bool => {
+            StaticArgument::Bool(yaml_get(arguments, "m_BoolArgument")?.data.as_i64()? != 0)
+        }
+    };
+
+    Some(argument)
+}
+
+/// Reads an object reference, returning nothing for null references
+/// (`{fileID: 0}`)
+fn parse_object_pointer(node: &MarkedYaml, hierarchy: &FileHierarchy) -> Option<ObjectPointer> {
+    let file_id = yaml_file_id(node).filter(|id| *id != 0)?;
+    let guid = yaml_get(node, "guid").and_then(yaml_to_guid);
+
+    let object = match guid {
+        Some(_) => None,
+        None => hierarchy.object_path(file_id),
+    };
+
+    Some(ObjectPointer {
+        file_id,
+        guid,
+        object,
+    })
+}
+
+/// Looks up a key in a YAML hash node
+pub fn yaml_get<'a>(node: &'a MarkedYaml, key: &str) -> Option<&'a MarkedYaml> {
+    node.data
+        .as_hash()?
+        .get(&MarkedYaml::from(YamlData::String(key.to_owned())))
+}
+
+/// Reads the `fileID` of an object reference node (`{fileID: x}`)
+pub fn yaml_file_id(node: &MarkedYaml) -> Option<i64> {
+    yaml_get(node, "fileID")?.data.as_i64()
+}
+
+/// Reads a scalar node as a string. Unity does not quote strings that look like
+/// numbers or booleans, so those are converted back.
+pub fn yaml_to_string(node: &MarkedYaml) -> Option<String> {
+    match &node.data {
+        YamlData::String(s) | YamlData::Real(s) => Some(s.clone()),
+        YamlData::Integer(i) => Some(i.to_string()),
+        YamlData::Boolean(b) => Some(b.to_string()),
+        _ => None,
     }
+}
+
+/// Reads a numeric scalar node as a float, as whole numbers are parsed as
+/// integers
+pub fn yaml_to_f64(node: &MarkedYaml) -> Option<f64> {
+    match &node.data {
+        YamlData::Integer(i) => Some(*i as f64),
+        _ => node.data.as_f64(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crawler::tests::test_refset;
+    use crate::unityyaml::load_unity_yaml;
+
+    fn parse_calls(calls: &str) -> Vec<FoundCall> {
+        let content = format!(
+            "--- !u!114 &1\nMonoBehaviour:\n  m_OnClick:\n    m_PersistentCalls:\n      m_Calls:\n{}",
+            calls
+        );
+        let docs = load_unity_yaml(&content).unwrap();
+
+        let persistent_calls = yaml_get(&docs[0].yaml, "MonoBehaviour")
+            .and_then(|behaviour| yaml_get(behaviour, "m_OnClick"))
+            .and_then(|event| yaml_get(event, "m_PersistentCalls"))
+            .unwrap();
 
-    None
+        parse_persistent_calls(persistent_calls, &FileHierarchy::default())
+    }
+
+    #[test]
+    fn splits_assembly_qualified_type_names() {
+        let calls = parse_calls(
+            "      - m_Target: {fileID: 2}\n        m_TargetAssemblyTypeName: Game.Menu, Assembly-CSharp, Version=0.0.0.0, Culture=neutral, PublicKeyToken=null\n        m_MethodName: OnPlay\n",
+        );
+
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].method_name, "OnPlay");
+        assert_eq!(calls[0].typename.as_deref(), Some("Game.Menu"));
+        assert_eq!(calls[0].assembly.as_deref(), Some("Assembly-CSharp"));
+    }
+
+    #[test]
+    fn leaves_missing_type_names_unresolved() {
+        let calls = parse_calls(
+            "      - m_Target: {fileID: 2}\n        m_MethodName: OnPlay\n      - m_Target: {fileID: 2}\n        m_TargetAssemblyTypeName: \n        m_MethodName: OnQuit\n",
+        );
+
+        assert_eq!(calls.len(), 2);
+        assert!(calls
+            .iter()
+            .all(|call| call.typename.is_none() && call.assembly.is_none()));
+    }
+
+    const SOURCE: &str = "5ab2d8c3e1f04a7b9c6d2e8f1a3b5c7d";
+
+    /// A call to `Game.Menu.OnPlay` with the given serialized mode, call state
+    /// and argument values
+    fn parse_call_with(target: &str, mode: i64, call_state: i64, arguments: &str) -> FoundCall {
+        let mut calls = parse_calls(&format!(
+            "      - m_Target: {}\n        m_TargetAssemblyTypeName: Game.Menu, Assembly-CSharp\n        m_MethodName: OnPlay\n        m_Mode: {}\n        m_Arguments:\n{}        m_CallState: {}\n",
+            target, mode, arguments, call_state
+        ));
+
+        assert_eq!(calls.len(), 1);
+        calls.remove(0)
+    }
+
+    const ARGUMENTS: &str = "          m_ObjectArgument: {fileID: 11400000, guid: 0123456789abcdef0123456789abcdef, type: 2}\n          m_ObjectArgumentAssemblyTypeName: Game.Settings, Assembly-CSharp\n          m_IntArgument: 7\n          m_FloatArgument: 0.5\n          m_StringArgument: Hello\n          m_BoolArgument: 1\n";
+
+    #[test]
+    fn parses_the_argument_of_the_listener_mode() {
+        let expected = [
+            (0, ListenerMode::Dynamic, None),
+            (1, ListenerMode::Void, None),
+            (
+                2,
+                ListenerMode::Object,
+                Some(StaticArgument::Object {
+                    object: Some(ObjectPointer {
+                        file_id: 11400000,
+                        guid: Some("0123456789abcdef0123456789abcdef".to_owned()),
+                        object: None,
+                    }),
+                    type_name: Some("Game.Settings, Assembly-CSharp".to_owned()),
+                }),
+            ),
+            (3, ListenerMode::Int, Some(StaticArgument::Int(7))),
+            (4, ListenerMode::Float, Some(StaticArgument::Float(0.5))),
+            (
+                5,
+                ListenerMode::String,
+                Some(StaticArgument::String("Hello".to_owned())),
+            ),
+            (6, ListenerMode::Bool, Some(StaticArgument::Bool(true))),
+        ];
+
+        for (serialized, mode, argument) in expected {
+            let call = parse_call_with("{fileID: 0}", serialized, 2, ARGUMENTS);
+
+            assert_eq!(call.details.mode, Some(mode));
+            assert_eq!(call.details.argument, argument);
+        }
+    }
+
+    #[test]
+    fn parses_unset_arguments_as_defaults() {
+        let arguments = "          m_ObjectArgument: {fileID: 0}\n          m_ObjectArgumentAssemblyTypeName: UnityEngine.Object, UnityEngine\n          m_IntArgument: 0\n          m_FloatArgument: 0\n          m_StringArgument: \n          m_BoolArgument: 0\n";
+
+        let object = parse_call_with("{fileID: 0}", 2, 2, arguments);
+        assert_eq!(
+            object.details.argument,
+            Some(StaticArgument::Object {
+                object: None,
+                type_name: Some("UnityEngine.Object, UnityEngine".to_owned()),
+            })
+        );
+
+        let string = parse_call_with("{fileID: 0}", 5, 2, arguments);
+        assert_eq!(
+            string.details.argument,
+            Some(StaticArgument::String(String::new()))
+        );
+
+        let boolean = parse_call_with("{fileID: 0}", 6, 2, arguments);
+        assert_eq!(boolean.details.argument, Some(StaticArgument::Bool(false)));
+    }
+
+    #[test]
+    fn maps_serialized_call_states() {
+        let expected = [
+            (0, Some(CallState::Off)),
+            (1, Some(CallState::EditorAndRuntime)),
+            (2, Some(CallState::RuntimeOnly)),
+            (3, None),
+        ];
+
+        for (serialized, call_state) in expected {
+            let call = parse_call_with("{fileID: 0}", 1, serialized, ARGUMENTS);
+            assert_eq!(call.details.call_state, call_state);
+        }
+    }
+
+    #[test]
+    fn leaves_out_null_targets() {
+        let unset = parse_call_with("{fileID: 0}", 1, 2, ARGUMENTS);
+        assert_eq!(unset.details.target, None);
+
+        let local = parse_call_with("{fileID: 5}", 1, 2, ARGUMENTS);
+        assert_eq!(
+            local.details.target,
+            Some(ObjectPointer {
+                file_id: 5,
+                guid: None,
+                object: None,
+            })
+        );
+    }
+
+    fn method_override(method: &str) -> String {
+        format!(
+            "--- !u!1001 &10\nPrefabInstance:\n  m_Modification:\n    m_Modifications:\n    - target: {{fileID: 20, guid: {}, type: 3}}\n      propertyPath: m_OnClick.m_PersistentCalls.m_Calls.Array.data[0].m_MethodName\n      value: {}\n      objectReference: {{fileID: 0}}\n",
+            SOURCE, method
+        )
+    }
+
+    fn modifications(docs: &[UnityDocument]) -> &[MarkedYaml] {
+        yaml_get(&docs[0].yaml, "PrefabInstance")
+            .and_then(|instance| yaml_get(instance, "m_Modification"))
+            .and_then(|modification| yaml_get(modification, "m_Modifications"))
+            .and_then(|modifications| modifications.data.as_vec())
+            .unwrap()
+    }
+
+    #[test]
+    fn links_modified_calls_to_the_calls_they_override() {
+        let docs = load_unity_yaml(&method_override("OnQuit")).unwrap();
+        let calls = collect_modified_calls(modifications(&docs));
+
+        assert_eq!(calls.len(), 1);
+
+        let (source, call) = &calls[0];
+
+        assert_eq!(
+            source.as_ref(),
+            Some(&SourceCall {
+                prefab: SOURCE.to_owned(),
+                file_id: 20,
+                property: "m_OnClick.m_PersistentCalls.m_Calls.Array.data[0]".to_owned(),
+            })
+        );
+        assert_eq!(
+            yaml_get(call, "m_MethodName").and_then(yaml_to_string),
+            Some("OnQuit".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn resolves_calls_through_the_source_prefab() {
+        let source_docs = load_unity_yaml(
+            "--- !u!114 &20\nMonoBehaviour:\n  m_OnClick:\n    m_PersistentCalls:\n      m_Calls:\n      - m_Target: {fileID: 30}\n        m_TargetAssemblyTypeName: Game.Menu, Assembly-CSharp\n        m_MethodName: OnPlay\n",
+        )
+        .unwrap();
+
+        let source = SourceCall {
+            prefab: SOURCE.to_owned(),
+            file_id: 20,
+            property: "m_OnClick.m_PersistentCalls.m_Calls.Array.data[0]".to_owned(),
+        };
+
+        let refs = test_refset();
+
+        assert_eq!(
+            resolve_source_call(&source, &source_docs, &refs).await,
+            Some(("Game.Menu".to_owned(), "Assembly-CSharp".to_owned()))
+        );
+
+        let missing = SourceCall {
+            property: "m_OnClick.m_PersistentCalls.m_Calls.Array.data[1]".to_owned(),
+            ..source
+        };
+
+        assert_eq!(
+            resolve_source_call(&missing, &source_docs, &refs).await,
+            None
+        );
+    }
+
+    #[test]
+    fn overlays_overridden_fields_onto_the_source_call() {
+        let source_docs = load_unity_yaml(
+            "--- !u!114 &20\nMonoBehaviour:\n  m_OnClick:\n    m_PersistentCalls:\n      m_Calls:\n      - m_Target: {fileID: 30}\n        m_TargetAssemblyTypeName: Game.Menu, Assembly-CSharp\n        m_MethodName: OnPlay\n        m_Mode: 3\n        m_Arguments:\n          m_ObjectArgument: {fileID: 0}\n          m_ObjectArgumentAssemblyTypeName: UnityEngine.Object, UnityEngine\n          m_IntArgument: 5\n          m_FloatArgument: 0\n          m_StringArgument: \n          m_BoolArgument: 0\n        m_CallState: 1\n",
+        )
+        .unwrap();
+
+        let instance = format!(
+            "{}    - target: {{fileID: 20, guid: {}, type: 3}}\n      propertyPath: m_OnClick.m_PersistentCalls.m_Calls.Array.data[0].m_Arguments.m_IntArgument\n      value: 9\n      objectReference: {{fileID: 0}}\n",
+            method_override("OnQuit"),
+            SOURCE
+        );
+        let docs = load_unity_yaml(&instance).unwrap();
+        let calls = collect_modified_calls(modifications(&docs));
+        let (source, call) = &calls[0];
+
+        assert!(!is_complete_call(call));
+
+        // Without the source prefab, only the overridden fields are known
+        let alone = parse_call(call, &FileHierarchy::default()).unwrap();
+        assert_eq!(alone.method_name, "OnQuit");
+        assert_eq!(alone.typename, None);
+        assert_eq!(alone.details.mode, None);
+        assert_eq!(alone.details.argument, None);
+        assert_eq!(alone.details.call_state, None);
+
+        let mut merged = source_call_yaml(source.as_ref().unwrap(), &source_docs).unwrap();
+        overlay_fields(&mut merged, call);
+
+        let found = parse_call(&merged, &FileHierarchy::default()).unwrap();
+        assert_eq!(found.method_name, "OnQuit");
+        assert_eq!(found.typename.as_deref(), Some("Game.Menu"));
+        assert_eq!(found.details.mode, Some(ListenerMode::Int));
+        assert_eq!(found.details.argument, Some(StaticArgument::Int(9)));
+        assert_eq!(found.details.call_state, Some(CallState::EditorAndRuntime));
+
+        // The target is an object of the source prefab
+        assert_eq!(
+            found.details.target,
+            Some(ObjectPointer {
+                file_id: 30,
+                guid: Some(SOURCE.to_owned()),
+                object: None,
+            })
+        );
+    }
+
+    #[test]
+    fn names_object_reference_properties() {
+        let docs = load_unity_yaml(
+            "--- !u!23 &1\nMeshRenderer:\n  m_Materials:\n  - {fileID: 2100000, guid: 11111111111111111111111111111111, type: 2}\n  - {fileID: 2100000, guid: 22222222222222222222222222222222, type: 2}\n  m_Settings:\n    m_Lightmap: {fileID: 2800000, guid: 33333333333333333333333333333333, type: 3}\n  m_Modifications:\n  - target: {fileID: 1, guid: 44444444444444444444444444444444, type: 3}\n    propertyPath: m_Sprite\n    value: \n    objectReference: {fileID: 21300000, guid: 55555555555555555555555555555555, type: 3}\n",
+        )
+        .unwrap();
+
+        let renderer = yaml_get(&docs[0].yaml, "MeshRenderer").unwrap();
+
+        let mut found = Vec::new();
+        find_object_refs_recursive(renderer, "", &mut found);
+
+        let properties: Vec<(&str, &str)> = found
+            .iter()
+            .map(|(object, _, property)| (object.guid.as_str(), property.as_str()))
+            .collect();
+
+        assert_eq!(
+            properties,
+            [
+                (
+                    "11111111111111111111111111111111",
+                    "m_Materials.Array.data[0]"
+                ),
+                (
+                    "22222222222222222222222222222222",
+                    "m_Materials.Array.data[1]"
+                ),
+                ("33333333333333333333333333333333", "m_Settings.m_Lightmap"),
+                (
+                    "44444444444444444444444444444444",
+                    "m_Modifications.Array.data[0].target"
+                ),
+                ("55555555555555555555555555555555", "m_Sprite"),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_call_property_paths() {
+        assert_eq!(
+            split_call_property(
+                "m_OnClick.m_PersistentCalls.m_Calls.Array.data[3].m_Arguments.m_IntArgument"
+            ),
+            Some((
+                "m_OnClick.m_PersistentCalls.m_Calls.Array.data[3]",
+                "m_Arguments.m_IntArgument"
+            ))
+        );
+        assert_eq!(split_call_property("m_Name"), None);
+    }
 }